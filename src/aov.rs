@@ -0,0 +1,282 @@
+use crate::math::color::Color;
+use crate::math::space::{Pos, Vec3};
+use crate::math::hit::HitResult;
+use crate::scene::TestScene;
+use crate::image::Layer;
+use crate::rng::{hash, to_unit};
+
+/// Arbitrary output variable: something about the first thing a camera ray
+/// hits, rendered next to the color.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Aov {
+	/// Color of the surface without any lighting.
+	Albedo,
+	/// Surface normal, not flipped towards the camera.
+	Normal,
+	/// Distance from the camera along the view direction, which is infinite
+	/// where nothing was hit. The nearest of all samples.
+	Depth,
+	/// Position in the world.
+	Position,
+	/// Index of the object that was hit, see `HitResult::object`, or -1 for
+	/// nothing and for fog. Taken from the first sample, since IDs can't be
+	/// averaged.
+	Object,
+	/// Number of the material that was hit, see `TestScene::material_id`, or
+	/// -1 for nothing. Taken from the first sample.
+	Material,
+	/// Number of paths traced through the pixel.
+	Paths
+}
+
+/// How the samples of an AOV are combined.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Accumulation {
+	Average,
+	Min,
+	First,
+	Sum
+}
+
+impl Aov {
+	pub const ALL: [Aov; 7] = [Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Position, Aov::Object, Aov::Material, Aov::Paths];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Aov::Albedo => "albedo",
+			Aov::Normal => "normal",
+			Aov::Depth => "depth",
+			Aov::Position => "position",
+			Aov::Object => "object",
+			Aov::Material => "material",
+			Aov::Paths => "paths"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Aov> {
+		Aov::ALL.iter().copied().find(|aov| aov.name() == name)
+	}
+
+	/// Names of the channels, as used in OpenEXR files.
+	pub fn channels(self) -> &'static [&'static str] {
+		match self {
+			Aov::Albedo => &["R", "G", "B"],
+			Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+			Aov::Depth => &["Z"],
+			Aov::Object | Aov::Material => &["id"],
+			Aov::Paths => &["count"]
+		}
+	}
+
+	pub fn accumulation(self) -> Accumulation {
+		match self {
+			Aov::Albedo | Aov::Normal | Aov::Position => Accumulation::Average,
+			Aov::Depth => Accumulation::Min,
+			Aov::Object | Aov::Material => Accumulation::First,
+			Aov::Paths => Accumulation::Sum
+		}
+	}
+
+	/// Writes the value of one sample into `out`. `view` is where the camera
+	/// was when the ray was shot.
+	pub fn write(self, scene: &TestScene, view: Pos, hit: Option<HitResult>, out: &mut [f64]) {
+		let id = |id: Option<usize>| id.map_or(-1., |id| id as f64);
+
+		match (self, hit) {
+			(Aov::Albedo, Some(hit)) => {
+				let albedo = hit.material.albedo(hit);
+				out.copy_from_slice(&[albedo.0, albedo.1, albedo.2]);
+			}
+			(Aov::Normal, Some(hit)) => write_vec3(out, hit.normal),
+			(Aov::Position, Some(hit)) => write_vec3(out, hit.pos()),
+			(Aov::Albedo, None) | (Aov::Normal, None) | (Aov::Position, None) => write_vec3(out, Vec3::default()),
+			(Aov::Depth, hit) => out[0] = hit.map_or(f64::INFINITY, |hit| {
+				(hit.pos() - view.translation).dot(Vec3::new(0., 0., -1.).rotated_by(view.rotation))
+			}),
+			(Aov::Object, hit) => out[0] = id(hit.and_then(|hit| hit.object)),
+			(Aov::Material, hit) => out[0] = id(hit.and_then(|hit| scene.material_id(hit.material))),
+			(Aov::Paths, _) => out[0] = 1.
+		}
+	}
+}
+
+fn write_vec3(out: &mut [f64], vec3: Vec3) {
+	out.copy_from_slice(&[vec3.x, vec3.y, vec3.z]);
+}
+
+/// Set of AOVs to render. The order is the order they're stored in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aovs(Vec<Aov>);
+
+impl Aovs {
+	pub fn new(aovs: &[Aov]) -> Self {
+		let mut list = vec![];
+
+		for &aov in aovs {
+			if !list.contains(&aov) {
+				list.push(aov);
+			}
+		}
+
+		Aovs(list)
+	}
+
+	pub fn all() -> Self {
+		Aovs::new(&Aov::ALL)
+	}
+
+	pub fn list(&self) -> &[Aov] {
+		&self.0
+	}
+
+	pub fn contains(&self, aov: Aov) -> bool {
+		self.0.contains(&aov)
+	}
+
+	/// Number of values per pixel, including the three of the color.
+	pub fn stride(&self) -> usize {
+		3 + self.0.iter().map(|aov| aov.channels().len()).sum::<usize>()
+	}
+
+	/// Where the values of `aov` are in a pixel, after the color and the AOVs
+	/// before it.
+	pub fn offset(&self, aov: Aov) -> Option<usize> {
+		let mut offset = 3;
+
+		for &other in &self.0 {
+			if other == aov {
+				return Some(offset)
+			}
+
+			offset += other.channels().len();
+		}
+
+		None
+	}
+}
+
+/// Color and AOVs of a number of pixels, with all values of a pixel next to
+/// each other.
+#[derive(Clone, Debug)]
+pub struct Frame {
+	aovs: Aovs,
+	stride: usize,
+	values: Vec<f64>
+}
+
+impl Frame {
+	/// Black frame with all AOVs set to 0.
+	pub fn new(aovs: Aovs, len: usize) -> Self {
+		let stride = aovs.stride();
+		Frame { aovs, stride, values: vec![0.; len * stride] }
+	}
+
+	pub fn aovs(&self) -> &Aovs {
+		&self.aovs
+	}
+
+	pub fn len(&self) -> usize {
+		self.values.len() / self.stride
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	pub fn pixel(&self, index: usize) -> &[f64] {
+		&self.values[index * self.stride..(index + 1) * self.stride]
+	}
+
+	pub fn pixel_mut(&mut self, index: usize) -> &mut [f64] {
+		&mut self.values[index * self.stride..(index + 1) * self.stride]
+	}
+
+	/// Every pixel, one after another, for filling them in parallel.
+	pub fn pixels_mut(&mut self) -> std::slice::ChunksExactMut<'_, f64> {
+		self.values.chunks_exact_mut(self.stride)
+	}
+
+	#[cfg(not(debug_assertions))]
+	pub fn par_pixels_mut(&mut self) -> rayon::slice::ChunksExactMut<'_, f64> {
+		use rayon::slice::ParallelSliceMut;
+		self.values.par_chunks_exact_mut(self.stride)
+	}
+
+	pub fn color(&self, index: usize) -> Color {
+		let pixel = self.pixel(index);
+		Color(pixel[0], pixel[1], pixel[2])
+	}
+
+	pub fn colors(&self) -> Vec<Color> {
+		(0..self.len()).map(|index| self.color(index)).collect()
+	}
+
+	/// Values of `aov` for every pixel, or `None` if it wasn't rendered.
+	pub fn values(&self, aov: Aov) -> Option<Vec<&[f64]>> {
+		let offset = self.aovs.offset(aov)?;
+		let channels = aov.channels().len();
+
+		Some((0..self.len()).map(|index| &self.pixel(index)[offset..offset + channels]).collect())
+	}
+
+	/// Values of a three channel AOV, like the albedo, as colors.
+	pub fn aov_colors(&self, aov: Aov) -> Option<Vec<Color>> {
+		Some(self.values(aov)?.iter().map(|v| Color(v[0], v[1], v[2])).collect())
+	}
+
+	/// Values of a three channel AOV, like the normals, as vectors.
+	pub fn aov_vectors(&self, aov: Aov) -> Option<Vec<Vec3>> {
+		Some(self.values(aov)?.iter().map(|v| Vec3::new(v[0], v[1], v[2])).collect())
+	}
+
+	/// The color as an unnamed layer, followed by a layer for every AOV.
+	pub fn layers(&self) -> Vec<Layer> {
+		let mut layers = vec![Layer::color("", &self.colors())];
+
+		for &aov in self.aovs.list() {
+			let values = self.values(aov).unwrap().iter()
+				.flat_map(|v| v.iter().map(|&v| v as f32))
+				.collect();
+
+			layers.push(Layer::new(aov.name(), aov.channels(), values));
+		}
+
+		layers
+	}
+
+	/// Turns an AOV into colors between 0 and 1 for looking at. Distances are
+	/// scaled to the range of the image, and IDs get random colors.
+	pub fn visualize(&self, aov: Aov) -> Option<Vec<Color>> {
+		let values = self.values(aov)?;
+
+		let max = values.iter().map(|v| v[0]).filter(|v| v.is_finite()).fold(0., f64::max);
+
+		Some(match aov {
+			Aov::Albedo => self.aov_colors(aov)?,
+			Aov::Normal => values.iter().map(|v| Color(v[0] + 1., v[1] + 1., v[2] + 1.) / 2.).collect(),
+			Aov::Position => {
+				let positions = self.aov_vectors(aov)?;
+				let min = positions.iter().fold(Vec3::broadcast(f64::INFINITY), |a, p| a.min_by_component(*p));
+				let max = positions.iter().fold(Vec3::broadcast(f64::NEG_INFINITY), |a, p| a.max_by_component(*p));
+				let size = (max - min).max_by_component(Vec3::broadcast(1e-10));
+
+				positions.iter().map(|&p| {
+					let scaled = (p - min) / size;
+					Color(scaled.x, scaled.y, scaled.z)
+				}).collect()
+			}
+			Aov::Depth => values.iter().map(|v| match v[0] {
+				depth if depth.is_finite() && max > 0. => Color::splat(1. - depth / max),
+				_ => Color::default()
+			}).collect(),
+			Aov::Object | Aov::Material => values.iter().map(|v| match v[0] {
+				id if id < 0. => Color::default(),
+				id => {
+					let bits = hash(&[id as u64]);
+					Color(to_unit(bits), to_unit(bits.rotate_left(21)), to_unit(bits.rotate_left(42)))
+				}
+			}).collect(),
+			Aov::Paths => values.iter().map(|v| Color::splat(if max > 0. { v[0] / max } else { 0. })).collect()
+		})
+	}
+}
@@ -0,0 +1,473 @@
+use std::io::Write;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Instant;
+use in_one_weekend::scene::TestScene;
+use in_one_weekend::loader::{self, CameraDescription};
+use in_one_weekend::checkpoint::{self, Checkpoint, Settings};
+use in_one_weekend::progressive::ProgressiveBuffer;
+use in_one_weekend::image;
+use in_one_weekend::aov::{Aov, Aovs};
+use in_one_weekend::integrator::PathTracer;
+use in_one_weekend::math::color::{Color, ToneMapper};
+use in_one_weekend::math::color_space::ColorSpace;
+use in_one_weekend::math::spectrum::Spectral;
+use in_one_weekend::rng::{Sampler, Independent, Stratified, Halton, Sobol, BlueNoise};
+
+#[cfg(not(debug_assertions))]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+const USAGE: &str = "Usage: render [options] <output.png|output.ppm|output.exr|output.pfm>
+
+OpenEXR and PFM outputs are linear and also have the AOVs, as layers of the
+OpenEXR file or as extra PFM files next to the output.
+
+Options:
+  --scene <file.ron>    Scene to render (default is the built-in example)
+  --width <pixels>      Image width (default 640)
+  --height <pixels>     Image height (default 480)
+  --spp <samples>       Samples per pixel, or the most any pixel gets with
+                        --threshold (default 64)
+  --threshold <error>   Keep sampling only the pixels whose relative error is
+                        above this, and stop once none are left
+  --min-spp <samples>   Samples every pixel gets before --threshold kicks in
+                        (default 16)
+  --heatmap <file>      Also write an image of the error of every pixel
+                        relative to --threshold
+  --exposure <factor>   Exposure correction (default 1)
+  --output-space <name> Color space of the output: rec709, rec2020, acescg or p3
+                        (default rec709). PNG and PPM outputs always use the
+                        sRGB transfer curve
+  --tone-map <name>     Tone curve for PNG and PPM outputs: hejl, aces, agx,
+                        reinhard, hable or neutral (default hejl)
+  --white-point <value> Brightness that ends up white after tone mapping
+                        (default depends on the tone curve)
+  --sampler <name>      Where random numbers come from: independent, stratified,
+                        halton, sobol or blue-noise (default independent)
+  --seed <number>       Seed for the random numbers; the same seed renders the
+                        same image (default 0)
+  --aovs <list>         AOVs for OpenEXR and PFM outputs, separated by commas:
+                        albedo, normal, depth, position, object, material and
+                        paths, or all (default albedo,normal, or whatever the
+                        checkpoint has with --resume)
+  --checkpoint <file>   Save the progress to this file every now and then and
+                        at the end, to carry on with --resume later
+  --checkpoint-interval <seconds>
+                        Time between checkpoints (default 60)
+  --resume <file>       Carry on from a checkpoint, which has to be of the
+                        same scene file, camera, sampler, seed, tracing and
+                        size
+  --spectrum <mode>     How light is traced: rgb, or spectral with a few
+                        wavelengths per path, which is slower but shows
+                        dispersion (default rgb)
+  --max-depth <count>   Maximum number of surfaces a path can hit (default 16)
+  --roulette <count>    Bounces before Russian roulette starts (default 3)
+  --pos <x,y,z>         Camera position (overrides the scene)
+  --yaw <radians>       Camera yaw (overrides the scene)
+  --pitch <radians>     Camera pitch (overrides the scene)
+  --fov <degrees>       Field of view, measured the same way as the scene's
+                        (overrides the scene)
+  --aperture <radius>   Lens radius, 0 for a pinhole (overrides the scene)
+  --focus <distance>    Focus distance (overrides the scene)";
+
+struct Options {
+	output: String,
+	scene: Option<String>,
+	width: usize,
+	height: usize,
+	spp: u64,
+	threshold: Option<f64>,
+	min_spp: u64,
+	heatmap: Option<String>,
+	exposure: f64,
+	output_space: ColorSpace,
+	tone_mapper: ToneMapper,
+	white_point: Option<f64>,
+	sampler: String,
+	seed: u64,
+	aovs: Option<Aovs>,
+	checkpoint: Option<String>,
+	checkpoint_interval: f64,
+	resume: Option<String>,
+	integrator: PathTracer,
+	spectral: bool,
+	pos: Option<(f64, f64, f64)>,
+	yaw: Option<f64>,
+	pitch: Option<f64>,
+	fov: Option<f64>,
+	aperture: Option<f64>,
+	focus: Option<f64>
+}
+
+fn parse_args() -> Result<Options, String> {
+	let mut options = Options {
+		output: String::new(),
+		scene: None,
+		width: 640,
+		height: 480,
+		spp: 64,
+		threshold: None,
+		min_spp: 16,
+		heatmap: None,
+		exposure: 1.,
+		output_space: ColorSpace::Rec709,
+		tone_mapper: ToneMapper::default(),
+		white_point: None,
+		sampler: "independent".into(),
+		seed: 0,
+		aovs: None,
+		checkpoint: None,
+		checkpoint_interval: 60.,
+		resume: None,
+		integrator: PathTracer::default(),
+		spectral: false,
+		pos: None,
+		yaw: None,
+		pitch: None,
+		fov: None,
+		aperture: None,
+		focus: None
+	};
+
+	let mut output = None;
+	let mut args = std::env::args().skip(1);
+
+	while let Some(arg) = args.next() {
+		if !arg.starts_with("--") {
+			if output.replace(arg).is_some() {
+				return Err("more than one output file given".into())
+			}
+
+			continue
+		}
+
+		let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+		let invalid = || format!("invalid value for {}: {:?}", arg, value);
+
+		match arg.as_str() {
+			"--scene" => options.scene = Some(value),
+			"--width" => options.width = value.parse().map_err(|_| invalid())?,
+			"--height" => options.height = value.parse().map_err(|_| invalid())?,
+			"--spp" => options.spp = value.parse().map_err(|_| invalid())?,
+			"--threshold" => options.threshold = Some(value.parse().map_err(|_| invalid())?),
+			"--min-spp" => options.min_spp = value.parse().map_err(|_| invalid())?,
+			"--heatmap" => options.heatmap = Some(value),
+			"--exposure" => options.exposure = value.parse().map_err(|_| invalid())?,
+			"--output-space" => options.output_space = ColorSpace::from_name(&value).ok_or_else(invalid)?,
+			"--tone-map" => options.tone_mapper = ToneMapper::from_name(&value).ok_or_else(invalid)?,
+			"--white-point" => options.white_point = Some(value.parse().map_err(|_| invalid())?),
+			"--sampler" => options.sampler = value,
+			"--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+			"--aovs" => options.aovs = Some(match value.as_str() {
+				"all" => Aovs::all(),
+				_ => Aovs::new(&value.split(',')
+					.map(|name| Aov::from_name(name.trim()))
+					.collect::<Option<Vec<_>>>()
+					.ok_or_else(invalid)?)
+			}),
+			"--checkpoint" => options.checkpoint = Some(value),
+			"--checkpoint-interval" => options.checkpoint_interval = value.parse().map_err(|_| invalid())?,
+			"--resume" => options.resume = Some(value),
+			"--spectrum" => options.spectral = match value.as_str() {
+				"rgb" => false,
+				"spectral" => true,
+				_ => return Err(invalid())
+			},
+			"--max-depth" => options.integrator.max_depth = value.parse().map_err(|_| invalid())?,
+			"--roulette" => options.integrator.roulette_depth = value.parse().map_err(|_| invalid())?,
+			"--yaw" => options.yaw = Some(value.parse().map_err(|_| invalid())?),
+			"--pitch" => options.pitch = Some(value.parse().map_err(|_| invalid())?),
+			"--fov" => options.fov = Some(value.parse().map_err(|_| invalid())?),
+			"--aperture" => options.aperture = Some(value.parse().map_err(|_| invalid())?),
+			"--focus" => options.focus = Some(value.parse().map_err(|_| invalid())?),
+			"--pos" => {
+				let components = value.split(',')
+					.map(|c| c.trim().parse::<f64>())
+					.collect::<Result<Vec<_>, _>>()
+					.map_err(|_| invalid())?;
+
+				match components.as_slice() {
+					&[x, y, z] => options.pos = Some((x, y, z)),
+					_ => return Err(invalid())
+				}
+			}
+			_ => return Err(format!("unknown option {}", arg))
+		}
+	}
+
+	options.output = output.ok_or("no output file given")?;
+
+	if !image::is_supported(&options.output) && !image::is_supported_hdr(&options.output) {
+		return Err(format!("unsupported image format: {}", options.output))
+	}
+
+	if options.width == 0 || options.height == 0 || options.spp == 0 {
+		return Err("width, height and spp must be at least 1".into())
+	}
+
+	if options.threshold.map_or(false, |threshold| threshold <= 0.) {
+		return Err("threshold must be positive".into())
+	}
+
+	if let Some(heatmap) = &options.heatmap {
+		if options.threshold.is_none() {
+			return Err("--heatmap needs --threshold".into())
+		}
+
+		if !image::is_supported(heatmap) {
+			return Err(format!("unsupported image format: {}", heatmap))
+		}
+	}
+
+	if !SAMPLERS.contains(&options.sampler.as_str()) {
+		return Err(format!("unknown sampler {}", options.sampler))
+	}
+
+	if options.fov.map_or(false, |fov| fov <= 0. || fov >= 180.) {
+		return Err("fov must be between 0 and 180 degrees".into())
+	}
+
+	if options.white_point.map_or(false, |white_point| white_point <= 0.) {
+		return Err("white point must be positive".into())
+	}
+
+	if options.checkpoint_interval <= 0. {
+		return Err("checkpoint interval must be positive".into())
+	}
+
+	if options.focus.map_or(false, |focus| focus <= 0.) {
+		return Err("focus distance must be positive".into())
+	}
+
+	Ok(options)
+}
+
+const SAMPLERS: [&str; 5] = ["independent", "stratified", "halton", "sobol", "blue-noise"];
+
+fn sampler(name: &str, spp: u64) -> Arc<dyn Sampler> {
+	match name {
+		"stratified" => Arc::new(Stratified::new(spp.min(u32::MAX as u64) as u32)),
+		"halton" => Arc::new(Halton),
+		"sobol" => Arc::new(Sobol::new()),
+		"blue-noise" => Arc::new(BlueNoise::new()),
+		_ => Arc::new(Independent)
+	}
+}
+
+// exits if the checkpoint can't be read or doesn't fit the render
+fn resume(path: &str, settings: &Settings, options: &Options) -> ProgressiveBuffer {
+	let checkpoint = match Checkpoint::load(path) {
+		Ok(checkpoint) => checkpoint,
+		Err(err) => {
+			eprintln!("error: couldn't load {}: {}", path, err);
+			exit(1)
+		}
+	};
+
+	let matches = checkpoint.matches(settings);
+	let progressive = checkpoint.progressive;
+
+	let error = if !matches {
+		Some("it's of a different scene, camera or sampler".to_string())
+	} else if (progressive.width(), progressive.height()) != (options.width, options.height) {
+		Some(format!("it's {}x{}", progressive.width(), progressive.height()))
+	} else if options.aovs.as_ref().map_or(false, |aovs| aovs != progressive.frame().aovs()) {
+		Some("it has different AOVs".to_string())
+	} else {
+		None
+	};
+
+	if let Some(error) = error {
+		eprintln!("error: can't resume from {}: {}", path, error);
+		exit(1)
+	}
+
+	println!("resuming from {} at {} spp", path, progressive.spp());
+	progressive
+}
+
+fn save_checkpoint(path: &str, settings: &Settings, progressive: &ProgressiveBuffer) {
+	if let Err(err) = checkpoint::save(path, settings, progressive) {
+		eprintln!("\nerror: couldn't write {}: {}", path, err);
+		exit(1)
+	}
+}
+
+fn main() {
+	let options = match parse_args() {
+		Ok(options) => options,
+		Err(err) => {
+			eprintln!("error: {}\n\n{}", err, USAGE);
+			exit(2)
+		}
+	};
+
+	let (width, height) = (options.width, options.height);
+
+	let (mut scene, mut pose) = match &options.scene {
+		Some(path) => match loader::load(path) {
+			Ok(loaded) => loaded,
+			Err(err) => {
+				eprintln!("error: couldn't load {}: {}", path, err);
+				exit(1)
+			}
+		},
+		None => (TestScene::example(), CameraDescription::default())
+	};
+
+	pose.position = options.pos.unwrap_or(pose.position);
+	pose.yaw = options.yaw.unwrap_or(pose.yaw);
+	pose.pitch = options.pitch.unwrap_or(pose.pitch);
+	pose.fov = options.fov.map_or(pose.fov, |fov| pose.fov.with_degrees(fov));
+	pose.aperture = options.aperture.unwrap_or(pose.aperture);
+	pose.focus_distance = options.focus.unwrap_or(pose.focus_distance);
+
+	scene.build_bvh();
+	scene.collect_lights();
+	scene.collect_materials();
+	let integrator = PathTracer {
+		spectral: options.spectral.then(|| Arc::new(Spectral::new(scene.working_space))),
+		..options.integrator.clone()
+	};
+
+	let mut camera = pose.build();
+	camera.integrator = Box::new(integrator.clone());
+	camera.sampler = sampler(&options.sampler, options.spp);
+	camera.seed = options.seed;
+
+	// the scene was just loaded from the file, so it can be read again
+	let source = options.scene.as_ref().map_or(String::new(), |path| std::fs::read_to_string(path).unwrap_or_default());
+
+	// strata depend on the sample count
+	let sampler_name = match options.sampler.as_str() {
+		"stratified" => format!("stratified {}", options.spp),
+		name => name.to_string()
+	};
+
+	let settings = Settings { scene: &source, camera: &pose, sampler: &sampler_name, seed: options.seed, integrator: &integrator };
+
+	let mut progressive = match &options.resume {
+		Some(path) => resume(path, &settings, &options),
+		None => ProgressiveBuffer::new(width, height, options.aovs.clone().unwrap_or_else(|| Aovs::new(&[Aov::Albedo, Aov::Normal])))
+	};
+
+	let aovs = progressive.frame().aovs().clone();
+	let first = progressive.spp() + 1;
+	let resumed_samples: u64 = progressive.samples().iter().sum();
+
+	let start = Instant::now();
+	let mut last_checkpoint = start;
+
+	for sample in first..=options.spp {
+		match options.threshold {
+			Some(threshold) if sample > options.min_spp => {
+				let pending = progressive.pending(threshold, options.min_spp);
+
+				if pending.is_empty() {
+					eprint!("\nconverged after {} samples", sample - 1);
+					break
+				}
+
+				progressive.update_pixels(&pending, &camera.render_pixels(&scene, width, height, &pending, sample - 1, &aovs));
+
+				let elapsed = start.elapsed().as_secs_f64();
+				eprint!("\rsample {}/{} ({:.1}s elapsed, {} pixels left)   ", sample, options.spp, elapsed, pending.len());
+			}
+			_ => {
+				progressive.update(&camera.render(&scene, width, height, sample - 1, &aovs));
+
+				let elapsed = start.elapsed().as_secs_f64();
+				let remaining = elapsed / (sample - first + 1) as f64 * (options.spp - sample) as f64;
+
+				eprint!("\rsample {}/{} ({:.1}s elapsed, {:.1}s remaining)   ", sample, options.spp, elapsed, remaining);
+			}
+		}
+
+		std::io::stderr().flush().ok();
+
+		if let Some(path) = &options.checkpoint {
+			if last_checkpoint.elapsed().as_secs_f64() >= options.checkpoint_interval {
+				save_checkpoint(path, &settings, &progressive);
+				last_checkpoint = Instant::now();
+			}
+		}
+	}
+
+	let elapsed = start.elapsed().as_secs_f64();
+	let samples: u64 = progressive.samples().iter().sum();
+	let spp = samples as f64 / (width * height) as f64;
+
+	eprintln!();
+	println!("rendered {}x{} at {:.1} spp on average in {:.2}s ({} samples per second)",
+		width, height, spp, elapsed, ((samples - resumed_samples) as f64 / elapsed) as u64);
+
+	if let Some(path) = &options.checkpoint {
+		save_checkpoint(path, &settings, &progressive);
+		println!("wrote {}", path);
+	}
+
+	if let (Some(path), Some(threshold)) = (&options.heatmap, options.threshold) {
+		let pixels: Vec<u32> = progressive.error_heatmap(threshold).iter().map(Color::to_srgb).collect();
+
+		if let Err(err) = image::save(path, &pixels, width, height) {
+			eprintln!("error: couldn't write {}: {}", path, err);
+			exit(1)
+		}
+
+		println!("wrote {}", path);
+	}
+
+	let output = scene.working_space.conversion(options.output_space);
+
+	if image::is_supported_hdr(&options.output) {
+		let mut layers = progressive.frame().layers();
+
+		// only the color is exposed, the AOVs are data
+		for value in &mut layers[0].values {
+			*value *= options.exposure as f32;
+		}
+
+		for layer in &mut layers {
+			if layer.name.is_empty() || layer.name == Aov::Albedo.name() {
+				for value in layer.values.chunks_exact_mut(3) {
+					let color = output.transform(Color(value[0] as f64, value[1] as f64, value[2] as f64));
+					value.copy_from_slice(&[color.0 as f32, color.1 as f32, color.2 as f32]);
+				}
+			}
+		}
+
+		match image::save_layers(&options.output, &layers, width, height) {
+			Ok(paths) => for path in paths {
+				println!("wrote {}", path.display());
+			},
+			Err(err) => {
+				eprintln!("error: couldn't write {}: {}", options.output, err);
+				exit(1)
+			}
+		}
+
+		return
+	}
+
+	let buf = progressive.frame().colors();
+
+	#[cfg(not(debug_assertions))]
+		let iter = buf.into_par_iter();
+
+	#[cfg(debug_assertions)]
+		let iter = buf.into_iter();
+
+	let tone_mapper = options.tone_mapper;
+	let white_point = options.white_point.unwrap_or_else(|| tone_mapper.default_white_point());
+
+	let pixels: Vec<u32> = iter
+		.map(|c| output.transform(c * options.exposure).tone_map(tone_mapper, white_point).to_srgb())
+		.collect();
+
+	if let Err(err) = image::save(&options.output, &pixels, width, height) {
+		eprintln!("error: couldn't write {}: {}", options.output, err);
+		exit(1)
+	}
+
+	println!("wrote {}", options.output);
+}
@@ -0,0 +1,411 @@
+use crate::hittable::Hittable;
+use crate::math::ray::Ray;
+use crate::math::hit::HitResult;
+use crate::math::space::Vec3;
+use crate::math::aabb::Aabb;
+use crate::light::Light;
+use crate::material::Material;
+use crate::math::color::Color;
+
+const BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+
+// relative costs used by the surface area heuristic
+const TRAVERSAL_COST: f64 = 1.;
+const INTERSECTION_COST: f64 = 2.;
+
+#[derive(Copy, Clone, Debug)]
+enum Node {
+	Leaf { aabb: Aabb, start: usize, count: usize },
+	Branch { aabb: Aabb, left: usize, right: usize }
+}
+
+impl Node {
+	fn aabb(&self) -> &Aabb {
+		match self {
+			Node::Leaf { aabb, .. } | Node::Branch { aabb, .. } => aabb
+		}
+	}
+}
+
+/// Bounding volume hierarchy built using the surface area heuristic. Can be
+/// used in place of a `TestScene`, or as a child of `CSG` and `Volume`, where
+/// it behaves like the union of all of its objects.
+///
+/// Objects without a bounding box (like `Plane`) are kept outside of the tree
+/// and tested against every ray.
+///
+/// Hits are labeled with the index of the object they belong to, in the order
+/// the objects were given to `new`.
+#[derive(Debug)]
+pub struct Bvh {
+	nodes: Vec<Node>,
+	objects: Vec<Box<dyn Hittable>>,
+	unbounded: Vec<Box<dyn Hittable>>,
+	// original indices of `objects` and `unbounded`
+	indices: Vec<usize>,
+	unbounded_indices: Vec<usize>
+}
+
+struct Primitive {
+	aabb: Aabb,
+	centroid: Vec3,
+	index: usize
+}
+
+fn axis(vec: Vec3, axis: usize) -> f64 {
+	match axis {
+		0 => vec.x,
+		1 => vec.y,
+		_ => vec.z
+	}
+}
+
+fn union_all(primitives: &[Primitive]) -> Aabb {
+	primitives.iter().skip(1).fold(primitives[0].aabb, |aabb, p| aabb.union(&p.aabb))
+}
+
+impl Bvh {
+	pub fn new(objects: Vec<Box<dyn Hittable>>) -> Self {
+		let mut bounded = Vec::with_capacity(objects.len());
+		let mut unbounded = vec![];
+		let mut primitives = Vec::with_capacity(objects.len());
+		let mut bounded_indices = Vec::with_capacity(objects.len());
+		let mut unbounded_indices = vec![];
+
+		for (original, object) in objects.into_iter().enumerate() {
+			match object.bounding_box() {
+				Some(aabb) => {
+					primitives.push(Primitive { aabb, centroid: aabb.centroid(), index: bounded.len() });
+					bounded.push(Some(object));
+					bounded_indices.push(original);
+				}
+				None => {
+					unbounded.push(object);
+					unbounded_indices.push(original);
+				}
+			}
+		}
+
+		let mut nodes = Vec::with_capacity(primitives.len() * 2);
+
+		if !primitives.is_empty() {
+			Self::build(&mut nodes, &mut primitives, 0);
+		}
+
+		// reorder the objects so that every leaf refers to a contiguous range
+		let objects = primitives.iter()
+			.map(|p| bounded[p.index].take().unwrap())
+			.collect();
+
+		let indices = primitives.iter().map(|p| bounded_indices[p.index]).collect();
+
+		Bvh { nodes, objects, unbounded, indices, unbounded_indices }
+	}
+
+	fn build(nodes: &mut Vec<Node>, primitives: &mut [Primitive], start: usize) -> usize {
+		let aabb = union_all(primitives);
+		let index = nodes.len();
+
+		nodes.push(Node::Leaf { aabb, start, count: primitives.len() });
+
+		if primitives.len() <= 1 {
+			return index
+		}
+
+		let split = match Self::find_split(&aabb, primitives) {
+			Some(split) => split,
+			None => return index
+		};
+
+		let (left_primitives, right_primitives) = primitives.split_at_mut(split);
+		let left = Self::build(nodes, left_primitives, start);
+		let right = Self::build(nodes, right_primitives, start + split);
+
+		nodes[index] = Node::Branch { aabb, left, right };
+		index
+	}
+
+	/// Partitions `primitives` along the cheapest binned split and returns the
+	/// index of the first primitive on the right side, or `None` if the
+	/// primitives are better off staying in a single leaf.
+	fn find_split(aabb: &Aabb, primitives: &mut [Primitive]) -> Option<usize> {
+		let centroid_bounds = Aabb::from_points(primitives.iter().map(|p| p.centroid))?;
+		let leaf_cost = INTERSECTION_COST * primitives.len() as f64;
+
+		let mut best: Option<(usize, f64, f64)> = None;
+
+		for split_axis in 0..3 {
+			let min = axis(centroid_bounds.min, split_axis);
+			let extent = axis(centroid_bounds.max, split_axis) - min;
+
+			if extent <= 0. {
+				continue
+			}
+
+			let bin_of = |p: &Primitive| {
+				(((axis(p.centroid, split_axis) - min) / extent * BINS as f64) as usize).min(BINS - 1)
+			};
+
+			let mut bins: [(Option<Aabb>, usize); BINS] = [(None, 0); BINS];
+
+			for primitive in primitives.iter() {
+				let bin = &mut bins[bin_of(primitive)];
+				bin.0 = Some(bin.0.map_or(primitive.aabb, |aabb| aabb.union(&primitive.aabb)));
+				bin.1 += 1;
+			}
+
+			// sweep from the right to get the cost of everything after each split
+			let mut right_costs = [0f64; BINS];
+			let mut right_aabb: Option<Aabb> = None;
+			let mut right_count = 0;
+
+			for bin in (1..BINS).rev() {
+				if let Some(aabb) = bins[bin].0 {
+					right_aabb = Some(right_aabb.map_or(aabb, |r| r.union(&aabb)));
+				}
+
+				right_count += bins[bin].1;
+				right_costs[bin] = right_aabb.map_or(0., |r| r.surface_area()) * right_count as f64;
+			}
+
+			let mut left_aabb: Option<Aabb> = None;
+			let mut left_count = 0;
+
+			for bin in 0..BINS - 1 {
+				if let Some(aabb) = bins[bin].0 {
+					left_aabb = Some(left_aabb.map_or(aabb, |l| l.union(&aabb)));
+				}
+
+				left_count += bins[bin].1;
+
+				if left_count == 0 || left_count == primitives.len() {
+					continue
+				}
+
+				let left_cost = left_aabb.map_or(0., |l| l.surface_area()) * left_count as f64;
+				let cost = TRAVERSAL_COST + INTERSECTION_COST *
+					(left_cost + right_costs[bin + 1]) / aabb.surface_area();
+
+				if best.map_or(true, |(_, _, best_cost)| cost < best_cost) {
+					let position = min + extent * (bin + 1) as f64 / BINS as f64;
+					best = Some((split_axis, position, cost));
+				}
+			}
+		}
+
+		match best {
+			Some((split_axis, position, cost)) if cost < leaf_cost || primitives.len() > MAX_LEAF_SIZE => {
+				let mut split = 0;
+
+				for i in 0..primitives.len() {
+					if axis(primitives[i].centroid, split_axis) < position {
+						primitives.swap(i, split);
+						split += 1;
+					}
+				}
+
+				if split == 0 || split == primitives.len() {
+					None
+				} else {
+					Some(split)
+				}
+			}
+			None if primitives.len() > MAX_LEAF_SIZE => {
+				// all centroids are in the same place, so just cut it in half
+				Some(primitives.len() / 2)
+			}
+			_ => None
+		}
+	}
+}
+
+impl Bvh {
+	// closest of what `trace` finds on the objects, visiting the nodes front to
+	// back so that the ones behind a hit can be skipped
+	fn closest_hit<'a>(&'a self, ray: &Ray, trace: impl Fn(&'a dyn Hittable, &Ray) -> Option<HitResult<'a>>) -> Option<HitResult<'a>> {
+		let mut hit: Option<HitResult> = None;
+
+		for (object, &index) in self.unbounded.iter().zip(&self.unbounded_indices) {
+			if let Some(this_hit) = trace(object.as_ref(), ray) {
+				let this_hit = HitResult { object: Some(index), ..this_hit };
+				hit = Some(hit.map_or(this_hit, |h| h.closer(this_hit)));
+			}
+		}
+
+		if self.nodes.is_empty() {
+			return hit
+		}
+
+		let mut stack = Vec::with_capacity(64);
+
+		if let Some((t, _)) = self.nodes[0].aabb().intersect(ray) {
+			stack.push((0, t));
+		}
+
+		while let Some((index, t)) = stack.pop() {
+			if hit.map_or(false, |h| h.t < t) {
+				continue
+			}
+
+			match self.nodes[index] {
+				Node::Leaf { start, count, .. } => {
+					for (object, &index) in self.objects[start..start + count].iter().zip(&self.indices[start..start + count]) {
+						if let Some(this_hit) = trace(object.as_ref(), ray) {
+							let this_hit = HitResult { object: Some(index), ..this_hit };
+							hit = Some(hit.map_or(this_hit, |h| h.closer(this_hit)));
+						}
+					}
+				}
+				Node::Branch { left, right, .. } => {
+					let left_t = self.nodes[left].aabb().intersect(ray).map(|(t, _)| t);
+					let right_t = self.nodes[right].aabb().intersect(ray).map(|(t, _)| t);
+
+					// push the farther child first so the nearer one is visited first
+					match (left_t, right_t) {
+						(Some(lt), Some(rt)) if lt < rt => {
+							stack.push((right, rt));
+							stack.push((left, lt));
+						}
+						(Some(lt), Some(rt)) => {
+							stack.push((left, lt));
+							stack.push((right, rt));
+						}
+						(Some(lt), None) => stack.push((left, lt)),
+						(None, Some(rt)) => stack.push((right, rt)),
+						(None, None) => {}
+					}
+				}
+			}
+		}
+
+		hit
+	}
+}
+
+impl Hittable for Bvh {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		self.closest_hit(ray, |object, ray| object.ray_trace(ray))
+	}
+
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		self.closest_hit(ray, |object, ray| object.ray_trace_surfaces(ray))
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		let mut transmittance = self.unbounded.iter()
+			.fold(Color::splat(1.), |transmittance, object| transmittance * object.transmittance(ray));
+
+		let mut stack = vec![];
+
+		if !self.nodes.is_empty() {
+			stack.push(0);
+		}
+
+		// every object along the ray counts, not just the closest
+		while let Some(index) = stack.pop() {
+			let node = &self.nodes[index];
+
+			if node.aabb().intersect(ray).is_none() {
+				continue
+			}
+
+			match *node {
+				Node::Leaf { start, count, .. } => {
+					for object in &self.objects[start..start + count] {
+						transmittance *= object.transmittance(ray);
+					}
+				}
+				Node::Branch { left, right, .. } => {
+					stack.push(left);
+					stack.push(right);
+				}
+			}
+		}
+
+		transmittance
+	}
+
+	// bounding boxes only hold the surfaces, and some shapes like `FinitePlane`
+	// are inside of a lot more than that, so nothing can be skipped
+	fn is_inside(&self, point: Vec3) -> bool {
+		self.objects.iter().chain(&self.unbounded).any(|o| o.is_inside(point))
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		if !self.unbounded.is_empty() {
+			return None
+		}
+
+		self.nodes.first().map(|node| *node.aabb())
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		for object in self.objects.iter().chain(self.unbounded.iter()) {
+			object.collect_lights(lights);
+		}
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		// in the original order, so that they don't depend on the tree
+		let mut objects: Vec<_> = self.indices.iter().zip(&self.objects)
+			.chain(self.unbounded_indices.iter().zip(&self.unbounded))
+			.collect();
+
+		objects.sort_by_key(|&(index, _)| *index);
+
+		for (_, object) in objects {
+			object.collect_materials(materials);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::material::lambertian::Lambertian;
+	use crate::math::color::Color;
+	use crate::scene::TestScene;
+	use crate::shape::plane::Plane;
+	use crate::shape::sphere::Sphere;
+
+	// spheres of all sizes, some of them overlapping, and a floor that has no
+	// bounding box
+	fn objects() -> Vec<Box<dyn Hittable>> {
+		let material = || Box::new(Lambertian::solid(Color::splat(0.5)));
+
+		let mut objects: Vec<Box<dyn Hittable>> = (0..100).map(|i| {
+			let (x, z) = ((i % 10) as f64, (i / 10) as f64);
+			let radius = 0.2 + (i % 7) as f64 * 0.15;
+
+			Box::new(Sphere { center: Vec3::new(x - 5., radius, -z), radius, material: material() }) as Box<dyn Hittable>
+		}).collect();
+
+		objects.push(Box::new(Plane { center: Vec3::new(0., 0., 0.), normal: Vec3::new(0., 1., 0.), material: material() }));
+		objects
+	}
+
+	#[test]
+	fn hits_the_same_as_going_through_every_object() {
+		let bvh = Bvh::new(objects());
+		let mut scene = TestScene::new();
+		scene.objects = objects();
+
+		for i in 0..40 {
+			for j in 0..30 {
+				let dir = Vec3::new(i as f64 - 20., 5.5 - j as f64, -30.);
+				let ray = Ray::new(Vec3::new(0.3, 4., 8.), dir);
+
+				let linear = scene.ray_trace(&ray);
+				let hit = bvh.ray_trace(&ray);
+				assert_eq!(hit.map(|hit| hit.t), linear.map(|hit| hit.t));
+
+				// the object is the one that's hit first on its own
+				if let Some(hit) = hit {
+					let object = scene.objects.iter().position(|object| object.ray_trace(&ray).map(|own| own.t) == Some(hit.t));
+					assert_eq!(hit.object, object);
+				}
+			}
+		}
+	}
+}
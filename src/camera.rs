@@ -1,133 +1,212 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use ultraviolet::{Lerp, Slerp};
 use crate::math::color::Color;
 use crate::hittable::Hittable;
+use crate::scene::TestScene;
 use crate::math::ray::Ray;
 use crate::math::space::{Pos, Vec3};
-use crate::math::hit::HitResult;
-use crate::rng::{get_wyrand, gen_wyrand};
+use crate::integrator::{Integrator, PathTracer};
+use crate::rng::{Sampler, Independent, PathSampler, set_sampler};
+use crate::aov::{Aov, Aovs, Frame};
 
 #[cfg(not(debug_assertions))]
-use rayon::iter::{IntoParallelIterator, ParallelIterator, IntoParallelRefIterator};
+use rayon::iter::{IntoParallelIterator, ParallelIterator, IndexedParallelIterator};
 
 #[cfg(not(debug_assertions))]
 use rayon::slice::ParallelSlice;
 
+/// Field of view in degrees, measured across the height or width of the image,
+/// or across whichever of them is shorter.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Fov {
+	Vertical(f64),
+	Horizontal(f64),
+	Shorter(f64)
+}
+
+impl Default for Fov {
+	// what the camera used to be hardcoded to
+	fn default() -> Self {
+		Fov::Shorter(2. * 0.25f64.atan().to_degrees())
+	}
+}
+
+impl Fov {
+	/// The same kind of field of view, but `degrees` wide.
+	pub fn with_degrees(self, degrees: f64) -> Self {
+		match self {
+			Fov::Vertical(_) => Fov::Vertical(degrees),
+			Fov::Horizontal(_) => Fov::Horizontal(degrees),
+			Fov::Shorter(_) => Fov::Shorter(degrees)
+		}
+	}
+}
+
 pub struct Camera {
 	pub pos: Pos,
-	pub blur_pos: Option<Pos>
+	/// Where the camera is at the end of the exposure, for motion blur.
+	pub blur_pos: Option<Pos>,
+	/// Part of the exposure during which the shutter is open, between 0 and 1.
+	pub shutter: (f64, f64),
+	pub integrator: Box<dyn Integrator>,
+	pub fov: Fov,
+	/// Radius of the lens. Zero makes a pinhole camera, where everything is in
+	/// focus.
+	pub aperture: f64,
+	/// Distance from the camera at which things are perfectly sharp.
+	pub focus_distance: f64,
+	/// Where all random numbers of a render come from.
+	pub sampler: Arc<dyn Sampler>,
+	/// Seed for the sampler.
+	pub seed: u64
 }
 
 impl Camera {
-	pub fn calculate_far_plane(pos: Pos, width: usize, height: usize) -> (Vec3, Vec3, Vec3) {
-		let cam_right: Vec3 = Vec3::new(1., 0., 0.).rotated_by(pos.rotation);
-		let cam_down: Vec3 = Vec3::new(0., -1., 0.).rotated_by(pos.rotation);
-
-		let plane_dist = 5000.;
-		let plane_min = 2500.;
-		let plane_width;
-		let plane_height;
-
-		if width > height {
-			plane_width = plane_min * width as f64 / height as f64;
-			plane_height = plane_min;
-		} else {
-			plane_width = plane_min;
-			plane_height = plane_min * height as f64 / width as f64;
+	/// How far camera rays reach.
+	pub const VIEW_DISTANCE: f64 = 5000.;
+
+	/// Pinhole camera with the default field of view and a path tracer.
+	pub fn new(pos: Pos) -> Self {
+		Camera {
+			pos,
+			blur_pos: None,
+			shutter: (0., 1.),
+			integrator: Box::new(PathTracer::default()),
+			fov: Fov::default(),
+			aperture: 0.,
+			focus_distance: 10.,
+			sampler: Arc::new(Independent),
+			seed: 0
 		}
+	}
 
-		let fp_right: Vec3 = cam_right * plane_width;
-		let fp_down: Vec3 = cam_down * plane_height;
-		let fp_topleft: Vec3 = pos * Vec3::new(0., 0., -plane_dist) - ((fp_right + fp_down) / 2.);
+	/// Position of the camera at `time` during the exposure.
+	pub fn pos_at(&self, time: f64) -> Pos {
+		match self.blur_pos {
+			Some(end) => Pos::new(
+				self.pos.translation.lerp(end.translation, time),
+				self.pos.rotation.slerp(end.rotation, time).normalized()
+			),
+			None => self.pos
+		}
+	}
+
+	/// Returns the top left corner of the far plane and the vectors along its
+	/// width and height, in camera space.
+	pub fn calculate_far_plane(&self, width: usize, height: usize) -> (Vec3, Vec3, Vec3) {
+		let aspect = width as f64 / height as f64;
+
+		let (fov, vertical) = match self.fov {
+			Fov::Vertical(fov) => (fov, true),
+			Fov::Horizontal(fov) => (fov, false),
+			Fov::Shorter(fov) => (fov, width > height)
+		};
+
+		let size = 2. * Self::VIEW_DISTANCE * (fov.to_radians() / 2.).tan();
+		let (plane_width, plane_height) = if vertical { (size * aspect, size) } else { (size, size / aspect) };
+
+		let fp_right = Vec3::new(plane_width, 0., 0.);
+		let fp_down = Vec3::new(0., -plane_height, 0.);
+		let fp_topleft: Vec3 = Vec3::new(0., 0., -Self::VIEW_DISTANCE) - ((fp_right + fp_down) / 2.);
 
 		(fp_topleft, fp_right, fp_down)
 	}
 
-	pub fn rays(pos: Pos, width: usize, height: usize) -> Vec<Ray> {
-		let (fp_topleft, fp_right, fp_down) = Self::calculate_far_plane(pos, width, height);
+	/// One ray for every pixel, for sample number `sample`.
+	pub fn rays(&self, width: usize, height: usize, sample: u64) -> Vec<Ray> {
+		self.rays_for(width, height, 0..width * height, sample).into_iter().map(|(ray, _)| ray).collect()
+	}
 
-		let mut rays = Vec::with_capacity(width * height);
+	/// Rays for only the given pixels, which are indices into the image.
+	pub fn pixel_rays(&self, width: usize, height: usize, pixels: &[usize], sample: u64) -> Vec<Ray> {
+		self.rays_for(width, height, pixels.iter().copied(), sample).into_iter().map(|(ray, _)| ray).collect()
+	}
+
+	// also returns the sampler of every pixel, so that the path traced from
+	// the ray continues where the camera left off
+	fn rays_for<I: ExactSizeIterator<Item = usize>>(&self, width: usize, height: usize, pixels: I, sample: u64) -> Vec<(Ray, PathSampler)> {
+		let (fp_topleft, fp_right, fp_down) = self.calculate_far_plane(width, height);
+
+		// rays through the lens are aimed at where the pinhole ray crosses the
+		// focus plane, and scaled to still reach the far plane
+		let lens_scale = Self::VIEW_DISTANCE / self.focus_distance;
+
+		let mut rays = Vec::with_capacity(pixels.len());
 
 		let wf = width as f64;
 		let hf = height as f64;
 
-		let rng = get_wyrand();
+		let (open, close) = self.shutter;
 
-		//let progress = rng.gen::<f64>() / 5.;
-		//let blur = Vec3::new(progress, progress / 2., 0.);
-		//let plane_origin = plane_origin + blur;
-		let origin = pos.translation;// + blur;
+		for pixel in pixels {
+			let (x, y) = (pixel % width, pixel / width);
+			let mut sampler = PathSampler::new(self.sampler.clone(), self.seed, (x, y), sample);
 
-		let mut y = 0.;
+			let xr = (x as f64 + sampler.next_f64()) / wf;
+			let yr = (y as f64 + sampler.next_f64()) / hf;
 
-		while y < hf {
-			let mut x = 0.;
+			let mut origin = Vec3::default();
+			let mut direction: Vec3 = fp_topleft + fp_right * xr + fp_down * yr;
 
-			while x < wf {
-				let xr = (x + gen_wyrand(rng)) / wf;
-				let yr = (y + gen_wyrand(rng)) / hf;
+			// drawn even for pinholes, so that the time is always the same dimension
+			let (lens_r, lens_theta) = (sampler.next_f64(), sampler.next_f64());
 
-				let direction: Vec3 = fp_topleft + fp_right * xr + fp_down * yr;
-				rays.push(Ray::new(origin, direction));
+			if self.aperture > 0. {
+				// uniformly distributed point on the lens
+				let r = self.aperture * lens_r.sqrt();
+				let theta = 2. * PI * lens_theta;
 
-				x += 1.;
+				origin = Vec3::new(r * theta.cos(), r * theta.sin(), 0.);
+				direction -= origin * lens_scale;
 			}
 
-			y += 1.;
+			let time = sampler.range(open, close);
+			let pos = self.pos_at(time);
+
+			rays.push((Ray::new(pos * origin, direction.rotated_by(pos.rotation)).with_time(time), sampler));
 		}
 
 		rays
 	}
 
-	pub fn render_combined(&self, scene: &dyn Hittable, width: usize, height: usize) -> Vec<(Color, Color, Vec3)> {
-		let rays = Self::rays(self.pos, width, height);
-
-		const REFLECTIONS: u8 = 5;
-
-		#[cfg(not(debug_assertions))]
-			let iter = rays.into_par_iter();
-
-		#[cfg(debug_assertions)]
-			let iter = rays.into_iter();
-
-		return iter
-			.map(|r| scene.ray_trace(&r))
-			.map(|r|
-				(
-					HitResult::get_color(r, scene, REFLECTIONS),
-					r.map(|r| r.material.albedo(r)).unwrap_or_default(),
-					r.map(|r| r.normal).unwrap_or_default(),
-				)
-			)
-			.collect();
+	/// Renders sample number `sample` of every pixel, along with `aovs`. The
+	/// same sample of the same camera and scene always comes out the same.
+	pub fn render(&self, scene: &TestScene, width: usize, height: usize, sample: u64, aovs: &Aovs) -> Frame {
+		self.render_rays(scene, self.rays_for(width, height, 0..width * height, sample), aovs)
 	}
 
-	pub fn separate_color(combined: &[(Color, Color, Vec3)]) -> Vec<Color> {
-		#[cfg(not(debug_assertions))]
-			let iter = combined.par_iter();
-
-		#[cfg(debug_assertions)]
-			let iter = combined.iter();
-
-		iter.map(|t| t.0).collect()
+	/// Renders one sample for each of the given pixels, in the same order.
+	pub fn render_pixels(&self, scene: &TestScene, width: usize, height: usize, pixels: &[usize], sample: u64, aovs: &Aovs) -> Frame {
+		self.render_rays(scene, self.rays_for(width, height, pixels.iter().copied(), sample), aovs)
 	}
 
-	pub fn separate_albedo(combined: &[(Color, Color, Vec3)]) -> Vec<Color> {
+	fn render_rays(&self, scene: &TestScene, rays: Vec<(Ray, PathSampler)>, aovs: &Aovs) -> Frame {
+		let mut frame = Frame::new(aovs.clone(), rays.len());
+
 		#[cfg(not(debug_assertions))]
-			let iter = combined.par_iter();
+			let iter = frame.par_pixels_mut().zip(rays.into_par_iter());
 
 		#[cfg(debug_assertions)]
-			let iter = combined.iter();
+			let iter = frame.pixels_mut().zip(rays.into_iter());
 
-		iter.map(|t| t.1).collect()
-	}
+		iter.for_each(|(pixel, (ray, sampler))| {
+			set_sampler(sampler);
 
-	pub fn separate_normals(combined: &[(Color, Color, Vec3)]) -> Vec<Vec3> {
-		#[cfg(not(debug_assertions))]
-			let iter = combined.par_iter();
+			let hit = scene.ray_trace(&ray);
+			let color = self.integrator.radiance(scene, &ray, hit);
+			pixel[..3].copy_from_slice(&[color.0, color.1, color.2]);
 
-		#[cfg(debug_assertions)]
-			let iter = combined.iter();
+			let view = self.pos_at(ray.time);
+
+			for &aov in aovs.list() {
+				let offset = aovs.offset(aov).unwrap();
+				aov.write(scene, view, hit, &mut pixel[offset..offset + aov.channels().len()]);
+			}
+		});
 
-		iter.map(|t| t.2).collect()
+		frame
 	}
 
 	pub fn separate_color_f32(buf: &[Color]) -> Vec<f32> {
@@ -166,15 +245,49 @@ impl Camera {
 			.collect()
 	}
 
-	pub fn render_color(&self, scene: &dyn Hittable, width: usize, height: usize) -> Vec<Color> {
-		Camera::separate_color(&self.render_combined(scene, width, height))
+	pub fn render_color(&self, scene: &TestScene, width: usize, height: usize, sample: u64) -> Vec<Color> {
+		self.render(scene, width, height, sample, &Aovs::default()).colors()
+	}
+
+	pub fn render_albedo(&self, scene: &TestScene, width: usize, height: usize, sample: u64) -> Vec<Color> {
+		self.render(scene, width, height, sample, &Aovs::new(&[Aov::Albedo])).aov_colors(Aov::Albedo).unwrap()
+	}
+
+	pub fn render_normals(&self, scene: &TestScene, width: usize, height: usize, sample: u64) -> Vec<Vec3> {
+		self.render(scene, width, height, sample, &Aovs::new(&[Aov::Normal])).aov_vectors(Aov::Normal).unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ultraviolet::DRotor3;
+
+	// a few samples of the example scene, which has most kinds of shapes,
+	// materials and lights in it
+	fn render(seed: u64) -> Vec<u64> {
+		let mut scene = TestScene::example();
+		scene.build_bvh();
+		// `Hittable` has methods with the same names
+		TestScene::collect_lights(&mut scene);
+		TestScene::collect_materials(&mut scene);
+
+		let mut camera = Camera::new(Pos::new(Vec3::new(0., 1., 10.), DRotor3::identity()));
+		camera.seed = seed;
+
+		(0..4)
+			.flat_map(|sample| camera.render_color(&scene, 24, 16, sample))
+			.flat_map(|color| vec![color.0.to_bits(), color.1.to_bits(), color.2.to_bits()])
+			.collect()
 	}
 
-	pub fn render_albedo(&self, scene: &dyn Hittable, width: usize, height: usize) -> Vec<Color> {
-		Camera::separate_albedo(&self.render_combined(scene, width, height))
+	#[test]
+	fn same_seed_renders_the_same() {
+		assert!(render(7) == render(7));
 	}
 
-	pub fn render_normals(&self, scene: &dyn Hittable, width: usize, height: usize) -> Vec<Vec3> {
-		Camera::separate_normals(&self.render_combined(scene, width, height))
+	#[test]
+	fn different_seed_renders_differently() {
+		assert!(render(7) != render(8));
 	}
 }
@@ -0,0 +1,204 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, Write};
+use std::path::Path;
+use crate::camera::Fov;
+use crate::integrator::PathTracer;
+use crate::loader::CameraDescription;
+use crate::progressive::ProgressiveBuffer;
+use crate::image::{invalid_data, read_line};
+
+const MAGIC: &str = "progressive checkpoint 1";
+
+/// Progressive render read back from a file written by `save`, so that a long
+/// render can be carried on instead of starting over.
+pub struct Checkpoint {
+	/// What the render was made with, see `Settings::fingerprint`.
+	pub fingerprint: u64,
+	/// Where the camera was, so that the viewer can go back there. Also part
+	/// of the fingerprint.
+	pub camera: CameraDescription,
+	pub progressive: ProgressiveBuffer
+}
+
+impl Checkpoint {
+	/// Whether carrying on with `settings` would render the same image as
+	/// before.
+	pub fn matches(&self, settings: &Settings) -> bool {
+		self.fingerprint == settings.fingerprint()
+	}
+
+	pub fn read<R: BufRead + Seek>(mut reader: R) -> io::Result<Self> {
+		if read_line(&mut reader)? != MAGIC {
+			return Err(invalid_data("not a checkpoint"))
+		}
+
+		let fingerprint = u64::from_str_radix(&read_line(&mut reader)?, 16).map_err(|_| invalid_data("invalid fingerprint"))?;
+		let camera = ron::de::from_str(&read_line(&mut reader)?).map_err(|_| invalid_data("invalid camera"))?;
+		let progressive = ProgressiveBuffer::read(reader)?;
+
+		Ok(Checkpoint { fingerprint, camera, progressive })
+	}
+
+	pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		Checkpoint::read(BufReader::new(File::open(path)?))
+	}
+}
+
+/// Everything that decides what a render looks like, apart from its size,
+/// which the checkpoint has anyway.
+#[derive(Copy, Clone, Debug)]
+pub struct Settings<'a> {
+	/// Contents of the scene file, or empty for the built-in example. Files
+	/// the scene refers to, like meshes and textures, aren't included.
+	pub scene: &'a str,
+	/// Where the camera is, which the viewer goes back to when resuming.
+	pub camera: &'a CameraDescription,
+	/// Name of the sampler, along with anything it was made with, like the
+	/// sample count of stratified sampling.
+	pub sampler: &'a str,
+	pub seed: u64,
+	pub integrator: &'a PathTracer
+}
+
+impl Settings<'_> {
+	/// Hash of all of the settings, which stays the same between runs and
+	/// builds.
+	pub fn fingerprint(&self) -> u64 {
+		let mut hasher = Fnv1a::default();
+		let camera = self.camera;
+
+		hasher.str(self.scene);
+		hasher.values(&[camera.position.0, camera.position.1, camera.position.2, camera.yaw, camera.pitch]);
+
+		let (kind, fov) = match camera.fov {
+			Fov::Vertical(fov) => (0, fov),
+			Fov::Horizontal(fov) => (1, fov),
+			Fov::Shorter(fov) => (2, fov)
+		};
+
+		hasher.u64(kind);
+		hasher.values(&[fov, camera.aperture, camera.focus_distance, camera.shutter.0, camera.shutter.1]);
+
+		let end_position = camera.end_position.map(|(x, y, z)| vec![x, y, z]);
+
+		for end in &[end_position, camera.end_yaw.map(|yaw| vec![yaw]), camera.end_pitch.map(|pitch| vec![pitch])] {
+			hasher.u64(end.is_some() as u64);
+			hasher.values(end.as_deref().unwrap_or(&[]));
+		}
+
+		hasher.str(self.sampler);
+		hasher.u64(self.seed);
+		hasher.u64(self.integrator.max_depth as u64);
+		hasher.u64(self.integrator.roulette_depth as u64);
+		hasher.u64(self.integrator.spectral.is_some() as u64);
+
+		hasher.0
+	}
+}
+
+/// Writes a checkpoint of `progressive`, which was rendered with `settings`.
+pub fn write<W: Write>(mut writer: W, settings: &Settings, progressive: &ProgressiveBuffer) -> io::Result<()> {
+	let description = ron::ser::to_string(settings.camera).map_err(io::Error::other)?;
+
+	write!(writer, "{}\n{:016x}\n{}\n", MAGIC, settings.fingerprint(), description)?;
+	progressive.write(writer)
+}
+
+/// Like `write`, but into the file at `path`. The checkpoint is written next
+/// to it first and then moved over, so that the last one survives being
+/// interrupted halfway through.
+pub fn save<P: AsRef<Path>>(path: P, settings: &Settings, progressive: &ProgressiveBuffer) -> io::Result<()> {
+	let path = path.as_ref();
+	let mut temporary = path.as_os_str().to_owned();
+	temporary.push(".tmp");
+
+	let mut writer = BufWriter::new(File::create(&temporary)?);
+	write(&mut writer, settings, progressive)?;
+	writer.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+
+	fs::rename(&temporary, path)
+}
+
+// unlike `DefaultHasher`, guaranteed to stay the same between runs and builds
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+	fn default() -> Self {
+		Fnv1a(0xcbf29ce484222325)
+	}
+}
+
+impl Fnv1a {
+	fn bytes(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+		}
+	}
+
+	fn u64(&mut self, value: u64) {
+		self.bytes(&value.to_le_bytes());
+	}
+
+	fn values(&mut self, values: &[f64]) {
+		for value in values {
+			self.u64(value.to_bits());
+		}
+	}
+
+	// with the length first, so that where one string ends is part of the hash
+	fn str(&mut self, value: &str) {
+		self.u64(value.len() as u64);
+		self.bytes(value.as_bytes());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+	use std::sync::Arc;
+	use crate::aov::Aovs;
+	use crate::math::color_space::ColorSpace;
+	use crate::math::spectrum::Spectral;
+
+	#[test]
+	fn fingerprint_changes_with_every_setting() {
+		let camera = CameraDescription::default();
+		let moved = CameraDescription { position: (0., 2., 0.), ..CameraDescription::default() };
+		// the same angle as the default, but measured across the height
+		let vertical = CameraDescription { fov: Fov::Vertical(2. * 0.25f64.atan().to_degrees()), ..CameraDescription::default() };
+		let same = CameraDescription::default();
+		let deeper = PathTracer { max_depth: 4, ..PathTracer::default() };
+		let integrator = PathTracer::default();
+		let settings = Settings { scene: "(objects: [])", camera: &camera, sampler: "stratified 16", seed: 1, integrator: &integrator };
+
+		assert_eq!(settings.fingerprint(), Settings { camera: &same, ..settings }.fingerprint());
+
+		for changed in &[
+			Settings { scene: "(objects: [ ])", ..settings },
+			Settings { camera: &moved, ..settings },
+			Settings { camera: &vertical, ..settings },
+			Settings { sampler: "stratified 64", ..settings },
+			Settings { seed: 2, ..settings },
+			Settings { integrator: &deeper, ..settings }
+		] {
+			assert_ne!(settings.fingerprint(), changed.fingerprint());
+		}
+	}
+
+	#[test]
+	fn spectral_checkpoint_resumes_spectral() {
+		let camera = CameraDescription::default();
+		let rgb = PathTracer::default();
+		let spectral = PathTracer { spectral: Some(Arc::new(Spectral::new(ColorSpace::Rec709))), ..PathTracer::default() };
+		let settings = |integrator| Settings { scene: "", camera: &camera, sampler: "sobol", seed: 0, integrator };
+
+		let mut file = vec![];
+		write(&mut file, &settings(&spectral), &ProgressiveBuffer::new(4, 3, Aovs::all())).unwrap();
+		let checkpoint = Checkpoint::read(Cursor::new(file)).unwrap();
+
+		assert!(checkpoint.matches(&settings(&spectral)));
+		assert!(!checkpoint.matches(&settings(&rgb)));
+		assert_eq!(checkpoint.progressive.width(), 4);
+	}
+}
@@ -0,0 +1,92 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::io;
+use std::path::Path;
+use ultraviolet::DRotor3;
+use crate::math::color::Color;
+use crate::math::space::Vec3;
+use crate::image::{self, Image};
+
+/// What rays that don't hit anything see.
+pub trait Environment: Send + Sync + Debug {
+	/// `dir` is normalized.
+	fn color(&self, dir: Vec3) -> Color;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Constant(pub Color);
+
+impl Environment for Constant {
+	fn color(&self, _dir: Vec3) -> Color {
+		self.0
+	}
+}
+
+/// Vertical gradient from `horizon` to `zenith`.
+#[derive(Copy, Clone, Debug)]
+pub struct Sky {
+	pub horizon: Color,
+	pub zenith: Color
+}
+
+impl Sky {
+	/// The sky from Ray Tracing in One Weekend.
+	pub fn classic() -> Self {
+		Sky { horizon: Color(1., 1., 1.), zenith: Color(0.5, 0.7, 1.0) }
+	}
+}
+
+impl Environment for Sky {
+	fn color(&self, dir: Vec3) -> Color {
+		self.horizon.lerp(&self.zenith, 0.5 * (dir.y + 1.))
+	}
+}
+
+/// Equirectangular (latitude/longitude) environment map. The center of the
+/// image is in the -Z direction, which is where the camera looks by default.
+#[derive(Debug)]
+pub struct EnvironmentMap {
+	pub image: Image,
+	pub rotation: DRotor3,
+	pub intensity: f64
+}
+
+impl EnvironmentMap {
+	pub fn new(image: Image) -> Self {
+		EnvironmentMap { image, rotation: DRotor3::identity(), intensity: 1. }
+	}
+
+	/// Loads a `.hdr` or `.pfm` file.
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		Ok(Self::new(image::load_hdr(path)?))
+	}
+
+	fn texel(&self, x: isize, y: isize) -> Color {
+		let width = self.image.width as isize;
+		let height = self.image.height as isize;
+
+		// wrap around horizontally, clamp vertically
+		self.image.get(x.rem_euclid(width) as usize, y.max(0).min(height - 1) as usize)
+	}
+}
+
+impl Environment for EnvironmentMap {
+	fn color(&self, dir: Vec3) -> Color {
+		let dir = dir.rotated_by(self.rotation.reversed());
+
+		let u = 0.5 + dir.x.atan2(-dir.z) / (2. * PI);
+		let v = dir.y.max(-1.).min(1.).acos() / PI;
+
+		// bilinear filtering, with texel centers at half-integer coordinates
+		let x = u * self.image.width as f64 - 0.5;
+		let y = v * self.image.height as f64 - 0.5;
+		let (x0, y0) = (x.floor(), y.floor());
+		let (fx, fy) = (x - x0, y - y0);
+		let (x0, y0) = (x0 as isize, y0 as isize);
+
+		let top = self.texel(x0, y0).lerp(&self.texel(x0 + 1, y0), fx);
+		let bottom = self.texel(x0, y0 + 1).lerp(&self.texel(x0 + 1, y0 + 1), fx);
+
+		top.lerp(&bottom, fy) * self.intensity
+	}
+}
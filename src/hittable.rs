@@ -1,12 +1,38 @@
 use crate::math::ray::Ray;
 use std::fmt::Debug;
+use crate::math::color::Color;
 use crate::math::hit::HitResult;
 use crate::math::space::Vec3;
+use crate::math::aabb::Aabb;
+use crate::light::Light;
+use crate::material::Material;
 
 pub trait Hittable: Send + Sync + Debug {
 	fn ray_trace(&self, ray: &Ray) -> Option<HitResult>;
 
+	/// Like `ray_trace`, but goes straight through participating media, which
+	/// `transmittance` accounts for instead. Used for shadow rays.
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		self.ray_trace(ray)
+	}
+
+	/// Fraction of the light that makes it through the participating media
+	/// along all of `ray`, ignoring any surfaces.
+	fn transmittance(&self, _ray: &Ray) -> Color {
+		Color::splat(1.)
+	}
+
 	fn is_inside(&self, point: Vec3) -> bool;
+
+	/// Box that fully contains the shape, or `None` if the shape is infinite.
+	fn bounding_box(&self) -> Option<Aabb>;
+
+	/// Adds a `Light` for every emissive shape, so that they can be sampled
+	/// directly.
+	fn collect_lights(&self, _lights: &mut Vec<Box<dyn Light>>) {}
+
+	/// Adds every material of the shape, so that they can be told apart.
+	fn collect_materials<'a>(&'a self, _materials: &mut Vec<&'a dyn Material>) {}
 }
 
 impl<T: Hittable> Hittable for &T {
@@ -14,7 +40,57 @@ impl<T: Hittable> Hittable for &T {
 		T::ray_trace(self, ray)
 	}
 
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		T::ray_trace_surfaces(self, ray)
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		T::transmittance(self, ray)
+	}
+
 	fn is_inside(&self, point: Vec3) -> bool {
 		T::is_inside(self, point)
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		T::bounding_box(self)
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		T::collect_lights(self, lights)
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		T::collect_materials(self, materials)
+	}
+}
+
+impl Hittable for Box<dyn Hittable> {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		self.as_ref().ray_trace(ray)
+	}
+
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		self.as_ref().ray_trace_surfaces(ray)
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		self.as_ref().transmittance(ray)
+	}
+
+	fn is_inside(&self, point: Vec3) -> bool {
+		self.as_ref().is_inside(point)
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		self.as_ref().bounding_box()
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		self.as_ref().collect_lights(lights)
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		self.as_ref().collect_materials(materials)
+	}
 }
\ No newline at end of file
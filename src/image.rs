@@ -0,0 +1,681 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::path::PathBuf;
+use crate::math::color::Color;
+use crate::math::space::Vec3;
+
+/// Floating point image, stored top to bottom.
+#[derive(Clone, Debug)]
+pub struct Image {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<Color>
+}
+
+impl Image {
+	pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+		assert_eq!(pixels.len(), width * height, "Buffer has an incorrect size!");
+		Image { width, height, pixels }
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> Color {
+		self.pixels[y * self.width + x]
+	}
+}
+
+/// Floating point image with named channels, like one of the passes of a
+/// render.
+#[derive(Clone, Debug)]
+pub struct Layer {
+	pub name: String,
+	pub channels: Vec<String>,
+	/// All channels of a pixel next to each other, top to bottom.
+	pub values: Vec<f32>
+}
+
+impl Layer {
+	pub fn new(name: &str, channels: &[&str], values: Vec<f32>) -> Self {
+		assert_eq!(values.len() % channels.len(), 0, "Buffer has an incorrect size!");
+
+		Layer {
+			name: name.to_string(),
+			channels: channels.iter().map(|c| c.to_string()).collect(),
+			values
+		}
+	}
+
+	/// `R`, `G` and `B` channels.
+	pub fn color(name: &str, pixels: &[Color]) -> Self {
+		let mut values = Vec::with_capacity(pixels.len() * 3);
+
+		for color in pixels {
+			values.extend_from_slice(&[color.0 as f32, color.1 as f32, color.2 as f32]);
+		}
+
+		Layer::new(name, &["R", "G", "B"], values)
+	}
+
+	/// `X`, `Y` and `Z` channels.
+	pub fn vector(name: &str, vectors: &[Vec3]) -> Self {
+		let mut values = Vec::with_capacity(vectors.len() * 3);
+
+		for vec3 in vectors {
+			values.extend_from_slice(&[vec3.x as f32, vec3.y as f32, vec3.z as f32]);
+		}
+
+		Layer::new(name, &["X", "Y", "Z"], values)
+	}
+
+	fn pixel_count(&self) -> usize {
+		self.values.len() / self.channels.len()
+	}
+}
+
+pub(crate) fn invalid_data<S: Into<String>>(message: S) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// number of values in an image with `channels` per pixel, checking a size
+// read from a header before anything is allocated for it
+fn image_size(width: usize, height: usize, channels: usize) -> io::Result<usize> {
+	if width == 0 || height == 0 {
+		return Err(invalid_data("image has no pixels"))
+	}
+
+	width.checked_mul(height)
+		.and_then(|pixels| pixels.checked_mul(channels))
+		.ok_or_else(|| invalid_data("image is too big"))
+}
+
+// empty vector with room for `len` items, which fails instead of aborting when
+// a broken header asks for more than can be allocated
+fn reserve<T>(len: usize) -> io::Result<Vec<T>> {
+	let mut vec = Vec::new();
+	vec.try_reserve_exact(len).map_err(|_| invalid_data("image is too big"))?;
+
+	Ok(vec)
+}
+
+fn to_io_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+	io::Error::other(err)
+}
+
+// pixels are packed as 0RGB, the same format that minifb and `Color::to_srgb` use
+fn to_rgb8(pixels: &[u32]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(pixels.len() * 3);
+
+	for pixel in pixels {
+		out.push((pixel >> 16) as u8);
+		out.push((pixel >> 8) as u8);
+		out.push(*pixel as u8);
+	}
+
+	out
+}
+
+pub fn write_png<W: Write>(writer: W, pixels: &[u32], width: usize, height: usize) -> io::Result<()> {
+	let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+	encoder.set_color(png::ColorType::RGB);
+	encoder.set_depth(png::BitDepth::Eight);
+
+	encoder.write_header().map_err(to_io_error)?
+		.write_image_data(&to_rgb8(pixels)).map_err(to_io_error)
+}
+
+pub fn write_ppm<W: Write>(mut writer: W, pixels: &[u32], width: usize, height: usize) -> io::Result<()> {
+	write!(writer, "P6\n{} {}\n255\n", width, height)?;
+	writer.write_all(&to_rgb8(pixels))
+}
+
+/// Writes a little endian portable float map. Only layers with one or three
+/// channels fit into one.
+pub fn write_pfm<W: Write>(mut writer: W, layer: &Layer, width: usize, height: usize) -> io::Result<()> {
+	assert_eq!(layer.pixel_count(), width * height, "Buffer has an incorrect size!");
+
+	let magic = match layer.channels.len() {
+		1 => "Pf",
+		3 => "PF",
+		count => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("PFM can't store {} channels", count)))
+	};
+
+	write!(writer, "{}\n{} {}\n-1\n", magic, width, height)?;
+
+	let row_len = width * layer.channels.len();
+
+	// rows are stored from the bottom up
+	for row in layer.values.chunks_exact(row_len.max(1)).rev() {
+		for value in row {
+			writer.write_all(&value.to_le_bytes())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Writes an uncompressed scanline OpenEXR file with 32-bit float channels.
+/// Every channel is prefixed with the name of its layer, like `albedo.R`,
+/// except for layers without a name.
+pub fn write_exr<W: Write>(mut writer: W, layers: &[Layer], width: usize, height: usize) -> io::Result<()> {
+	let mut channels: Vec<(String, &Layer, usize)> = vec![];
+
+	for layer in layers {
+		assert_eq!(layer.pixel_count(), width * height, "Buffer has an incorrect size!");
+
+		for (index, channel) in layer.channels.iter().enumerate() {
+			let name = match layer.name.as_str() {
+				"" => channel.clone(),
+				prefix => format!("{}.{}", prefix, channel)
+			};
+
+			channels.push((name, layer, index));
+		}
+	}
+
+	// readers expect the channels in alphabetical order
+	channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+	let long_names = channels.iter().any(|(name, _, _)| name.len() > 31);
+
+	let mut header = vec![];
+	header.extend_from_slice(&20000630i32.to_le_bytes());
+	header.extend_from_slice(&(2i32 | if long_names { 0x400 } else { 0 }).to_le_bytes());
+
+	let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+		header.extend_from_slice(name.as_bytes());
+		header.push(0);
+		header.extend_from_slice(kind.as_bytes());
+		header.push(0);
+		header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+		header.extend_from_slice(value);
+	};
+
+	let mut list = vec![];
+
+	for (name, _, _) in &channels {
+		list.extend_from_slice(name.as_bytes());
+		list.push(0);
+		// float pixels, not perceptually linear, reserved, x and y sampling
+		list.extend_from_slice(&2i32.to_le_bytes());
+		list.extend_from_slice(&[0, 0, 0, 0]);
+		list.extend_from_slice(&1i32.to_le_bytes());
+		list.extend_from_slice(&1i32.to_le_bytes());
+	}
+
+	list.push(0);
+
+	let mut window = vec![];
+
+	for value in &[0, 0, width as i32 - 1, height as i32 - 1] {
+		window.extend_from_slice(&value.to_le_bytes());
+	}
+
+	attribute("channels", "chlist", &list);
+	attribute("compression", "compression", &[0]);
+	attribute("dataWindow", "box2i", &window);
+	attribute("displayWindow", "box2i", &window);
+	attribute("lineOrder", "lineOrder", &[0]);
+	attribute("pixelAspectRatio", "float", &1f32.to_le_bytes());
+	attribute("screenWindowCenter", "v2f", &[0; 8]);
+	attribute("screenWindowWidth", "float", &1f32.to_le_bytes());
+	header.push(0);
+
+	// every scanline is its own chunk, listed in an offset table
+	let chunk_len = 8 + channels.len() * width * 4;
+	let first_chunk = header.len() + height * 8;
+
+	for y in 0..height {
+		header.extend_from_slice(&((first_chunk + y * chunk_len) as u64).to_le_bytes());
+	}
+
+	writer.write_all(&header)?;
+
+	let mut chunk = Vec::with_capacity(chunk_len);
+
+	for y in 0..height {
+		chunk.clear();
+		chunk.extend_from_slice(&(y as i32).to_le_bytes());
+		chunk.extend_from_slice(&((chunk_len - 8) as i32).to_le_bytes());
+
+		for (_, layer, index) in &channels {
+			let stride = layer.channels.len();
+
+			for x in 0..width {
+				chunk.extend_from_slice(&layer.values[(y * width + x) * stride + index].to_le_bytes());
+			}
+		}
+
+		writer.write_all(&chunk)?;
+	}
+
+	Ok(())
+}
+
+pub(crate) fn read_line<R: BufRead>(reader: &mut R) -> io::Result<String> {
+	let mut line = String::new();
+
+	if reader.read_line(&mut line)? == 0 {
+		return Err(io::ErrorKind::UnexpectedEof.into())
+	}
+
+	Ok(line.trim_end().to_string())
+}
+
+/// Reads a Radiance RGBE (`.hdr`) image.
+pub fn read_hdr<R: BufRead>(mut reader: R) -> io::Result<Image> {
+	let magic = read_line(&mut reader)?;
+
+	if magic != "#?RADIANCE" && magic != "#?RGBE" {
+		return Err(invalid_data("not a Radiance HDR file"))
+	}
+
+	loop {
+		let line = read_line(&mut reader)?;
+
+		if line.is_empty() {
+			break
+		}
+
+		if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+			return Err(invalid_data(format!("unsupported format {}", &line[7..])))
+		}
+	}
+
+	let resolution = read_line(&mut reader)?;
+	let parts: Vec<&str> = resolution.split_whitespace().collect();
+
+	let (width, height) = match parts.as_slice() {
+		&["-Y", height, "+X", width] => (
+			width.parse::<usize>().map_err(|_| invalid_data("invalid width"))?,
+			height.parse::<usize>().map_err(|_| invalid_data("invalid height"))?
+		),
+		_ => return Err(invalid_data(format!("unsupported orientation {}", resolution)))
+	};
+
+	let mut pixels = reserve(image_size(width, height, 1)?)?;
+	let mut scanline = reserve(width)?;
+	scanline.resize(width, [0u8; 4]);
+
+	for _ in 0..height {
+		read_hdr_scanline(&mut reader, &mut scanline)?;
+
+		pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+			if e == 0 {
+				Color::default()
+			} else {
+				let f = 2f64.powi(e as i32 - (128 + 8));
+				Color(r as f64 * f, g as f64 * f, b as f64 * f)
+			}
+		}));
+	}
+
+	Ok(Image::new(width, height, pixels))
+}
+
+fn read_hdr_scanline<R: Read>(reader: &mut R, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+	let width = scanline.len();
+	let mut first = [0u8; 4];
+	reader.read_exact(&mut first)?;
+
+	// new style run length encoding, where every channel is compressed separately
+	if width >= 8 && width < 32768 && first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0 {
+		if ((first[2] as usize) << 8 | first[3] as usize) != width {
+			return Err(invalid_data("scanline width mismatch"))
+		}
+
+		for channel in 0..4 {
+			let mut x = 0;
+
+			while x < width {
+				let mut count = [0u8; 1];
+				reader.read_exact(&mut count)?;
+				let count = count[0] as usize;
+
+				if count > 128 {
+					let count = count - 128;
+					let mut value = [0u8; 1];
+					reader.read_exact(&mut value)?;
+
+					if x + count > width {
+						return Err(invalid_data("run goes past the end of the scanline"))
+					}
+
+					scanline[x..x + count].iter_mut().for_each(|p| p[channel] = value[0]);
+					x += count;
+				} else {
+					if count == 0 || x + count > width {
+						return Err(invalid_data("invalid run length"))
+					}
+
+					let mut values = [0u8; 128];
+					reader.read_exact(&mut values[..count])?;
+
+					for value in &values[..count] {
+						scanline[x][channel] = *value;
+						x += 1;
+					}
+				}
+			}
+		}
+
+		return Ok(())
+	}
+
+	// flat pixels, possibly with old style runs
+	let mut x = 0;
+	let mut shift = 0;
+	let mut pixel = first;
+
+	loop {
+		if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 {
+			if x == 0 {
+				return Err(invalid_data("run at the start of a scanline"))
+			}
+
+			let count = (pixel[3] as usize) << shift;
+
+			if x + count > width {
+				return Err(invalid_data("run goes past the end of the scanline"))
+			}
+
+			let previous = scanline[x - 1];
+			scanline[x..x + count].iter_mut().for_each(|p| *p = previous);
+			x += count;
+			shift += 8;
+		} else {
+			scanline[x] = pixel;
+			x += 1;
+			shift = 0;
+		}
+
+		if x >= width {
+			return Ok(())
+		}
+
+		reader.read_exact(&mut pixel)?;
+	}
+}
+
+/// Reads a portable float map (`.pfm`), either color or greyscale.
+pub fn read_pfm<R: BufRead>(mut reader: R) -> io::Result<Image> {
+	// the header is three whitespace separated tokens followed by one
+	// whitespace character
+	let mut tokens = vec![];
+
+	while tokens.len() < 3 {
+		let mut token = String::new();
+
+		loop {
+			let mut byte = [0u8; 1];
+			reader.read_exact(&mut byte)?;
+
+			if byte[0].is_ascii_whitespace() {
+				if token.is_empty() {
+					continue
+				}
+
+				break
+			}
+
+			token.push(byte[0] as char);
+		}
+
+		tokens.push(token);
+	}
+
+	let channels = match tokens[0].as_str() {
+		"PF" => 3,
+		"Pf" => 1,
+		_ => return Err(invalid_data("not a PFM file"))
+	};
+
+	let width: usize = tokens[1].parse().map_err(|_| invalid_data("invalid width"))?;
+	let height: usize = tokens[2].parse().map_err(|_| invalid_data("invalid height"))?;
+	let scale = read_line(&mut reader)?.trim().parse::<f64>().map_err(|_| invalid_data("invalid scale"))?;
+	let little_endian = scale < 0.;
+
+	let len = image_size(width, height, channels * 4)?;
+	let mut data = reserve(len)?;
+	data.resize(len, 0u8);
+	reader.read_exact(&mut data)?;
+
+	let floats: Vec<f64> = data.chunks_exact(4).map(|b| {
+		let bytes = [b[0], b[1], b[2], b[3]];
+		(if little_endian { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }) as f64
+	}).collect();
+
+	let mut pixels = reserve(width * height)?;
+
+	// rows are stored from the bottom up
+	for y in (0..height).rev() {
+		let row = &floats[y * width * channels..(y + 1) * width * channels];
+
+		pixels.extend(row.chunks_exact(channels).map(|c| match *c {
+			[r, g, b] => Color(r, g, b),
+			_ => Color::splat(c[0])
+		}));
+	}
+
+	Ok(Image::new(width, height, pixels))
+}
+
+/// Reads a PNG image. Values are scaled to between 0 and 1 but not decoded
+/// from sRGB, and alpha is ignored.
+pub fn read_png<R: Read>(reader: R) -> io::Result<Image> {
+	let mut decoder = png::Decoder::new(reader);
+	decoder.set_transformations(png::Transformations::EXPAND);
+
+	let (info, mut reader) = decoder.read_info().map_err(to_io_error)?;
+	let (color_type, bit_depth) = reader.output_color_type();
+	let channels = color_type.samples();
+
+	// low bit depths are expanded to 8 bits
+	let sample_len = match bit_depth {
+		png::BitDepth::Eight => 1,
+		png::BitDepth::Sixteen => 2,
+		_ => return Err(invalid_data("unsupported bit depth"))
+	};
+
+	let (width, height) = (info.width as usize, info.height as usize);
+	let len = image_size(width, height, channels)?.checked_mul(sample_len).ok_or_else(|| invalid_data("image is too big"))?;
+
+	let mut data = reserve(len)?;
+	data.resize(len, 0u8);
+	reader.next_frame(&mut data).map_err(to_io_error)?;
+
+	// rows aren't padded, so the samples of every pixel follow each other
+	let samples: Vec<f64> = match bit_depth {
+		png::BitDepth::Eight => data.iter().map(|&b| b as f64 / 255.).collect(),
+		_ => data.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]]) as f64 / 65535.).collect()
+	};
+
+	let pixels = samples.chunks_exact(channels).map(|c| match c.len() {
+		1 | 2 => Color::splat(c[0]),
+		_ => Color(c[0], c[1], c[2])
+	}).collect();
+
+	Ok(Image::new(width, height, pixels))
+}
+
+/// Reads a binary (`P6`) or plain (`P3`) PPM image, scaled like `read_png`.
+pub fn read_ppm<R: BufRead>(mut reader: R) -> io::Result<Image> {
+	let mut tokens = vec![];
+
+	// the header is four whitespace separated tokens, which can be interrupted
+	// by comments, followed by one whitespace character
+	while tokens.len() < 4 {
+		let mut token = String::new();
+
+		loop {
+			let mut byte = [0u8; 1];
+			reader.read_exact(&mut byte)?;
+
+			if byte[0] == b'#' {
+				read_line(&mut reader)?;
+
+				if token.is_empty() {
+					continue
+				}
+
+				break
+			}
+
+			if byte[0].is_ascii_whitespace() {
+				if token.is_empty() {
+					continue
+				}
+
+				break
+			}
+
+			token.push(byte[0] as char);
+		}
+
+		tokens.push(token);
+	}
+
+	let binary = match tokens[0].as_str() {
+		"P6" => true,
+		"P3" => false,
+		_ => return Err(invalid_data("not a color PPM file"))
+	};
+
+	let width: usize = tokens[1].parse().map_err(|_| invalid_data("invalid width"))?;
+	let height: usize = tokens[2].parse().map_err(|_| invalid_data("invalid height"))?;
+	let max: u16 = tokens[3].parse().map_err(|_| invalid_data("invalid maximum value"))?;
+
+	if max == 0 {
+		return Err(invalid_data("invalid maximum value"))
+	}
+
+	let count = image_size(width, height, 3)?;
+
+	let samples: Vec<u16> = if binary {
+		let size = if max > 255 { 2 } else { 1 };
+		let len = image_size(width, height, 3 * size)?;
+		let mut data = reserve(len)?;
+		data.resize(len, 0u8);
+		reader.read_exact(&mut data)?;
+
+		if size == 2 {
+			data.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect()
+		} else {
+			data.into_iter().map(u16::from).collect()
+		}
+	} else {
+		let mut text = String::new();
+		reader.read_to_string(&mut text)?;
+
+		text.lines()
+			.map(|line| line.split('#').next().unwrap_or(""))
+			.flat_map(str::split_whitespace)
+			.take(count)
+			.map(|word| word.parse().map_err(|_| invalid_data(format!("invalid value {:?}", word))))
+			.collect::<io::Result<_>>()?
+	};
+
+	if samples.len() < count {
+		return Err(io::ErrorKind::UnexpectedEof.into())
+	}
+
+	let scale = 1. / max as f64;
+
+	let pixels = samples.chunks_exact(3)
+		.map(|c| Color(c[0] as f64 * scale, c[1] as f64 * scale, c[2] as f64 * scale))
+		.collect();
+
+	Ok(Image::new(width, height, pixels))
+}
+
+/// Loads a PNG or PPM image, without decoding sRGB.
+pub fn load_ldr<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+	let path = path.as_ref();
+
+	let read = match extension(path).as_deref() {
+		Some("png") => read_png::<BufReader<File>>,
+		Some("ppm") => read_ppm::<BufReader<File>>,
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported image format: {}", path.display())))
+	};
+
+	read(BufReader::new(File::open(path)?))
+}
+
+/// Loads a high dynamic range image, picking the format based on the file
+/// extension.
+pub fn load_hdr<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+	let path = path.as_ref();
+
+	let read = match extension(path).as_deref() {
+		Some("hdr") => read_hdr::<BufReader<File>>,
+		Some("pfm") => read_pfm::<BufReader<File>>,
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported image format: {}", path.display())))
+	};
+
+	read(BufReader::new(File::open(path)?))
+}
+
+fn extension(path: &Path) -> Option<String> {
+	path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase())
+}
+
+pub fn is_supported<P: AsRef<Path>>(path: P) -> bool {
+	matches!(extension(path.as_ref()).as_deref(), Some("png") | Some("ppm"))
+}
+
+pub fn is_supported_hdr<P: AsRef<Path>>(path: P) -> bool {
+	matches!(extension(path.as_ref()).as_deref(), Some("exr") | Some("pfm"))
+}
+
+/// Writes linear `layers` to `path`. OpenEXR files get all of them, while PFM
+/// files only hold one layer each, so every layer after the first goes into
+/// its own file next to `path`, like `render.albedo.pfm`. Returns the paths
+/// that were written.
+pub fn save_layers<P: AsRef<Path>>(path: P, layers: &[Layer], width: usize, height: usize) -> io::Result<Vec<PathBuf>> {
+	let path = path.as_ref();
+
+	match extension(path).as_deref() {
+		Some("exr") => {
+			let mut writer = BufWriter::new(File::create(path)?);
+			write_exr(&mut writer, layers, width, height)?;
+			writer.flush()?;
+
+			Ok(vec![path.to_path_buf()])
+		}
+		Some("pfm") => {
+			let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+			let mut paths = vec![];
+
+			for (index, layer) in layers.iter().enumerate() {
+				let layer_path = match index {
+					0 => path.to_path_buf(),
+					_ => path.with_file_name(format!("{}.{}.pfm", stem, layer.name))
+				};
+
+				let mut writer = BufWriter::new(File::create(&layer_path)?);
+				write_pfm(&mut writer, layer, width, height)?;
+				writer.flush()?;
+
+				paths.push(layer_path);
+			}
+
+			Ok(paths)
+		}
+		_ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported image format: {}", path.display())))
+	}
+}
+
+/// Writes 8-bit sRGB pixels to `path`, picking the format based on the file
+/// extension.
+pub fn save<P: AsRef<Path>>(path: P, pixels: &[u32], width: usize, height: usize) -> io::Result<()> {
+	assert_eq!(pixels.len(), width * height, "Buffer has an incorrect size!");
+
+	let path = path.as_ref();
+
+	let write = match extension(path).as_deref() {
+		Some("png") => write_png::<BufWriter<File>>,
+		Some("ppm") => write_ppm::<BufWriter<File>>,
+		_ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported image format: {}", path.display())))
+	};
+
+	write(BufWriter::new(File::create(path)?), pixels, width, height)
+}
@@ -0,0 +1,208 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use crate::math::color::Color;
+use crate::math::spectrum::{Spectral, SampledSpectrum, Wavelengths, WAVELENGTHS, set_hero_wavelength};
+use crate::math::ray::Ray;
+use crate::math::hit::HitResult;
+use crate::math::space::Vec3;
+use crate::hittable::Hittable;
+use crate::scene::TestScene;
+use crate::light::power_heuristic;
+use crate::rng::{gen_sampler_once, start_bounce};
+
+/// Works out how much light comes back along camera rays.
+pub trait Integrator: Send + Sync + Debug {
+	/// `hit` is what `ray` hits, which the camera already needs to know anyway.
+	fn radiance(&self, scene: &TestScene, ray: &Ray, hit: Option<HitResult>) -> Color;
+}
+
+/// Path tracer that samples lights directly at every diffuse bounce, and
+/// combines that with hitting them by chance using multiple importance
+/// sampling. Dim paths are ended early with Russian roulette.
+#[derive(Clone, Debug)]
+pub struct PathTracer {
+	/// Number of surfaces a path can hit, including the one hit by the camera
+	/// ray. Passing through the back of a portal doesn't count.
+	pub max_depth: u8,
+	/// Number of bounces after which paths may be terminated at random, with a
+	/// chance based on how much light they can still carry. Surviving paths
+	/// are made brighter to make up for it, so this doesn't add bias.
+	pub roulette_depth: u8,
+	/// Traces a few wavelengths per path instead of red, green and blue, which
+	/// is needed for dispersion. `None` renders in RGB.
+	pub spectral: Option<Arc<Spectral>>
+}
+
+impl Default for PathTracer {
+	fn default() -> Self {
+		PathTracer { max_depth: 16, roulette_depth: 3, spectral: None }
+	}
+}
+
+// what the light along a path is carried as. RGB is a spectrum of three
+// wavelengths that are red, green and blue, and a fourth that's always black
+#[derive(Copy, Clone)]
+enum Carrier<'a> {
+	Rgb,
+	Spectral(&'a Spectral, Wavelengths)
+}
+
+impl Carrier<'_> {
+	fn rgb(color: Color) -> SampledSpectrum {
+		SampledSpectrum([color.0, color.1, color.2, 0.])
+	}
+
+	fn light(self, color: Color) -> SampledSpectrum {
+		match self {
+			Carrier::Rgb => Carrier::rgb(color),
+			Carrier::Spectral(spectral, wavelengths) => spectral.illuminant(color, &wavelengths)
+		}
+	}
+
+	fn reflectance(self, color: Color) -> SampledSpectrum {
+		match self {
+			Carrier::Rgb => Carrier::rgb(color),
+			Carrier::Spectral(spectral, wavelengths) => spectral.reflectance(color, &wavelengths)
+		}
+	}
+
+	fn color(self, spectrum: SampledSpectrum) -> Color {
+		match self {
+			Carrier::Rgb => Color(spectrum.0[0], spectrum.0[1], spectrum.0[2]),
+			Carrier::Spectral(spectral, wavelengths) => spectral.to_color(spectrum, &wavelengths)
+		}
+	}
+}
+
+impl PathTracer {
+	// next event estimation: sends a ray straight towards a random light,
+	// weighted against the chance of hitting it by bouncing
+	fn sample_light(scene: &TestScene, result: HitResult, carrier: Carrier) -> SampledSpectrum {
+		let normal = result.normal_abs();
+		let origin: Vec3 = result.pos() + normal * 1e-10;
+
+		let sample = match scene.lights.sample(origin, result.ray.time) {
+			Some(sample) => sample,
+			None => return SampledSpectrum::default()
+		};
+
+		let to_light = sample.point - origin;
+		let dir = to_light.normalized();
+		let bsdf = result.material.eval(result, dir);
+		let pdf = result.material.pdf(result, dir);
+
+		if pdf <= 0. || sample.pdf <= 0. || bsdf == Color::default() {
+			return SampledSpectrum::default()
+		}
+
+		// overshoot a little so that the light itself is hit
+		let shadow_ray = Ray::new(origin, to_light * (1. + 1e-6)).with_time(result.ray.time);
+
+		match scene.ray_trace_surfaces(&shadow_ray) {
+			Some(hit) if (hit.pos() - sample.point).mag() <= to_light.mag() * 1e-4 => {
+				let transmittance = scene.transmittance(&Ray::new(origin, to_light).with_time(result.ray.time));
+
+				carrier.light(hit.material.emission(hit)) * carrier.reflectance(bsdf * transmittance) * (power_heuristic(sample.pdf, pdf) / sample.pdf)
+			}
+			_ => SampledSpectrum::default()
+		}
+	}
+}
+
+impl Integrator for PathTracer {
+	fn radiance(&self, scene: &TestScene, ray: &Ray, hit: Option<HitResult>) -> Color {
+		let carrier = match &self.spectral {
+			Some(spectral) => Carrier::Spectral(spectral, Wavelengths::sample(gen_sampler_once())),
+			None => Carrier::Rgb
+		};
+
+		set_hero_wavelength(match carrier {
+			Carrier::Spectral(_, wavelengths) => Some(wavelengths.hero()),
+			Carrier::Rgb => None
+		});
+
+		let mut color = SampledSpectrum::default();
+		let mut throughput = SampledSpectrum::splat(1.);
+		// only the hero wavelength is left, see `Scatter::wavelength_dependent`
+		let mut hero_only = false;
+		let mut ray = *ray;
+		let mut hit = hit;
+		let mut remaining = self.max_depth;
+		// density of the last bounce if the lights were also sampled from there
+		let mut last_pdf = None;
+		// the ray sent off by the last bounce, which null collisions carry on
+		let mut bounce_ray = ray;
+
+		for bounce in 0.. {
+			if remaining == 0 {
+				break
+			}
+
+			start_bounce(bounce);
+
+			let result = match hit {
+				Some(result) => result,
+				None => {
+					color += throughput * carrier.light(scene.environment_light(&ray));
+					break
+				}
+			};
+
+			if result.material.is_emissive() {
+				let weight = match last_pdf {
+					Some(pdf) => power_heuristic(pdf, scene.lights.pdf(&bounce_ray, bounce_ray.t_for(result.pos()))),
+					None => 1.
+				};
+
+				color += throughput * carrier.light(result.material.emission(result)) * weight;
+			}
+
+			let scatter = match result.material.sample(result) {
+				Some(scatter) => scatter,
+				None => break
+			};
+
+			if !scatter.passes_through {
+				remaining -= 1;
+			}
+
+			if !scatter.null {
+				// lights are only worth sampling if the bounced ray could still see them
+				last_pdf = scatter.pdf.filter(|_| remaining > 0);
+				bounce_ray = scatter.ray;
+
+				if last_pdf.is_some() {
+					color += throughput * PathTracer::sample_light(scene, result, carrier);
+				}
+			}
+
+			throughput *= carrier.reflectance(scatter.weight);
+
+			if scatter.wavelength_dependent && !hero_only {
+				if let Carrier::Spectral(..) = carrier {
+					// the other wavelengths would have gone elsewhere, so they
+					// are dropped, and the hero one makes up for them
+					let hero = throughput.0[0] * WAVELENGTHS as f64;
+					throughput = SampledSpectrum::default();
+					throughput.0[0] = hero;
+					hero_only = true;
+				}
+			}
+
+			if !scatter.passes_through && self.max_depth - remaining >= self.roulette_depth {
+				let survival = throughput.max_value().min(0.95);
+
+				if survival <= 0. || gen_sampler_once() >= survival {
+					break
+				}
+
+				throughput /= survival;
+			}
+
+			ray = scatter.ray;
+			hit = scene.ray_trace(&ray);
+		}
+
+		carrier.color(color)
+	}
+}
@@ -5,10 +5,19 @@ pub mod math;
 pub mod camera;
 pub mod hittable;
 pub mod material;
+pub mod medium;
 pub mod scene;
+pub mod bvh;
+pub mod environment;
+pub mod light;
+pub mod integrator;
 pub mod shape;
 pub mod progressive;
+pub mod aov;
+pub mod checkpoint;
 pub mod rng;
+pub mod image;
+pub mod loader;
 
 pub fn test_pattern(width: usize, height: usize) -> Vec<Color> {
 	let mut arr = Vec::with_capacity(width * height);
@@ -0,0 +1,225 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use crate::math::space::{Pos, Vec3};
+use crate::math::ray::Ray;
+use crate::rng::gen_sampler_once;
+
+/// Point on a light, picked by `Light::sample`.
+#[derive(Copy, Clone, Debug)]
+pub struct LightSample {
+	pub point: Vec3,
+	/// Probability density of picking this point, with respect to solid angle
+	/// as seen from the point that is being lit.
+	pub pdf: f64
+}
+
+/// Shape of an emissive object, used to send rays directly towards it. How
+/// much light the object actually gives off comes from its material, by
+/// tracing a ray towards the sampled point.
+pub trait Light: Send + Sync + Debug {
+	/// Picks a point on the light as it is at `time`.
+	fn sample(&self, origin: Vec3, time: f64) -> Option<LightSample>;
+
+	/// Returns the `t` at which `ray` hits the light and the density with which
+	/// `sample` would have picked that point, or `None` if it misses.
+	fn pdf(&self, ray: &Ray) -> Option<(f64, f64)>;
+}
+
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+	let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+
+	if a + b == 0. {
+		0.
+	} else {
+		a / (a + b)
+	}
+}
+
+/// Every light in a scene. Lights are sampled with equal probability.
+#[derive(Debug, Default)]
+pub struct Lights(pub Vec<Box<dyn Light>>);
+
+impl Lights {
+	pub fn sample(&self, origin: Vec3, time: f64) -> Option<LightSample> {
+		if self.0.is_empty() {
+			return None
+		}
+
+		let count = self.0.len();
+		let index = ((gen_sampler_once() * count as f64) as usize).min(count - 1);
+
+		self.0[index].sample(origin, time).map(|sample| LightSample { pdf: sample.pdf / count as f64, ..sample })
+	}
+
+	/// Density with which `sample` picks the point that `ray` hits at `t`, or 0
+	/// if that point is not on any light.
+	pub fn pdf(&self, ray: &Ray, t: f64) -> f64 {
+		let pdf: f64 = self.0.iter()
+			.filter_map(|light| light.pdf(ray))
+			.filter(|(light_t, _)| (light_t - t).abs() <= t * 1e-6 + 1e-12)
+			.map(|(_, pdf)| pdf)
+			.sum();
+
+		pdf / self.0.len().max(1) as f64
+	}
+}
+
+// two vectors that are perpendicular to `n` and each other
+pub(crate) fn basis(n: Vec3) -> (Vec3, Vec3) {
+	let helper = if n.x.abs() > 0.9 { Vec3::new(0., 1., 0.) } else { Vec3::new(1., 0., 0.) };
+	let u = n.cross(helper).normalized();
+
+	(u, n.cross(u))
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SphereLight {
+	pub center: Vec3,
+	pub radius: f64
+}
+
+impl SphereLight {
+	// cosine of the half-angle of the cone that the sphere covers
+	fn cos_max(&self, origin: Vec3) -> Option<f64> {
+		let dist_sq = (self.center - origin).mag_sq();
+		let radius_sq = self.radius * self.radius;
+
+		if dist_sq <= radius_sq {
+			None
+		} else {
+			Some((1. - radius_sq / dist_sq).sqrt())
+		}
+	}
+
+	fn intersect(&self, ray: &Ray) -> Option<f64> {
+		let diff: Vec3 = ray.pos - self.center;
+		let a = ray.dir.mag_sq();
+		let half_b = diff.dot(ray.dir);
+		let c = diff.mag_sq() - self.radius * self.radius;
+		let discriminant = half_b * half_b - a * c;
+
+		if discriminant < 0. {
+			None
+		} else {
+			Some((-half_b - discriminant.sqrt()) / a).filter(|t| *t >= 0.)
+		}
+	}
+}
+
+impl Light for SphereLight {
+	// samples the cone of directions that hit the sphere uniformly
+	fn sample(&self, origin: Vec3, _time: f64) -> Option<LightSample> {
+		let cos_max = self.cos_max(origin)?;
+		let axis = (self.center - origin).normalized();
+		let (u, v) = basis(axis);
+
+		let cos_theta = 1. - gen_sampler_once() * (1. - cos_max);
+		let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+		let phi = 2. * PI * gen_sampler_once();
+
+		let dir = u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin()) + axis * cos_theta;
+		let ray = Ray::new(origin, dir);
+
+		// grazing rays can just barely miss due to rounding
+		let t = self.intersect(&ray).unwrap_or_else(|| (self.center - origin).dot(dir));
+
+		Some(LightSample { point: ray.at(t), pdf: 1. / (2. * PI * (1. - cos_max)) })
+	}
+
+	fn pdf(&self, ray: &Ray) -> Option<(f64, f64)> {
+		let cos_max = self.cos_max(ray.pos)?;
+		let t = self.intersect(ray)?;
+
+		Some((t, 1. / (2. * PI * (1. - cos_max))))
+	}
+}
+
+/// Rectangle lying in the XZ plane of `pos`, like `FinitePlane`. Lights both
+/// sides.
+#[derive(Copy, Clone, Debug)]
+pub struct RectLight {
+	pub pos: Pos,
+	pub size: (f64, f64)
+}
+
+impl RectLight {
+	fn normal(&self) -> Vec3 {
+		Vec3::new(0., 1., 0.).rotated_by(self.pos.rotation)
+	}
+
+	// converts a density with respect to area into one with respect to solid angle
+	fn solid_angle_pdf(&self, origin: Vec3, point: Vec3) -> f64 {
+		let to_light = point - origin;
+		let dist_sq = to_light.mag_sq();
+		let cos = to_light.normalized().dot(self.normal()).abs();
+
+		if cos <= 0. {
+			0.
+		} else {
+			dist_sq / (cos * self.size.0 * self.size.1)
+		}
+	}
+}
+
+impl Light for RectLight {
+	fn sample(&self, origin: Vec3, _time: f64) -> Option<LightSample> {
+		let local = Vec3::new(
+			(gen_sampler_once() - 0.5) * self.size.0,
+			0.,
+			(gen_sampler_once() - 0.5) * self.size.1
+		);
+
+		let point = self.pos * local;
+		let pdf = self.solid_angle_pdf(origin, point);
+
+		if pdf > 0. {
+			Some(LightSample { point, pdf })
+		} else {
+			None
+		}
+	}
+
+	fn pdf(&self, ray: &Ray) -> Option<(f64, f64)> {
+		let normal = self.normal();
+		let denom = normal.dot(ray.dir);
+
+		if denom == 0. {
+			return None
+		}
+
+		let t = (self.pos.translation - ray.pos).dot(normal) / denom;
+
+		if t < 0. {
+			return None
+		}
+
+		let point = ray.at(t);
+		let local: Vec3 = self.pos.inversed() * point;
+
+		if local.x.abs() * 2. > self.size.0 || local.z.abs() * 2. > self.size.1 {
+			None
+		} else {
+			Some((t, self.solid_angle_pdf(ray.pos, point)))
+		}
+	}
+}
+
+/// Light that moves along with a `Moving` shape.
+#[derive(Debug)]
+pub struct MovingLight {
+	pub light: Box<dyn Light>,
+	pub velocity: Vec3
+}
+
+impl Light for MovingLight {
+	fn sample(&self, origin: Vec3, time: f64) -> Option<LightSample> {
+		let offset = self.velocity * time;
+		let sample = self.light.sample(origin - offset, time)?;
+
+		Some(LightSample { point: sample.point + offset, ..sample })
+	}
+
+	fn pdf(&self, ray: &Ray) -> Option<(f64, f64)> {
+		self.light.pdf(&Ray { pos: ray.pos - self.velocity * ray.time, ..*ray })
+	}
+}
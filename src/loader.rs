@@ -0,0 +1,639 @@
+//! Loads scenes from [RON](https://github.com/ron-rs/ron) files. See
+//! `scenes/example.ron` for a scene that uses most of the features.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use ultraviolet::DRotor3;
+use crate::math::color::Color;
+use crate::math::color_space::{ColorSpace, ColorMatrix};
+use crate::image::Image;
+use crate::math::space::{Pos, Vec3};
+use crate::hittable::Hittable;
+use crate::scene::TestScene;
+use crate::bvh::Bvh;
+use crate::camera::{Camera, Fov};
+use crate::material::Material;
+use crate::material::albedo::Albedo;
+use crate::material::albedo::solid::Solid;
+use crate::material::albedo::normals::Normals;
+use crate::material::albedo::texture::{ImageTexture, Encoding, Wrap};
+use crate::material::albedo::pattern::{Pattern, Checker, Noise, Turbulence, Marble, Wood};
+use crate::material::albedo::procedural::{Procedural, ColorRamp};
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::glossy::Glossy;
+use crate::material::emissive::Emissive;
+use crate::material::dielectric::Dielectric;
+use crate::material::mapper::Mapper;
+use crate::material::portal::Portal;
+use crate::material::composite::Composite;
+use crate::shape::sphere::Sphere;
+use crate::shape::plane::{Plane, FinitePlane};
+use crate::shape::triangle::Triangle;
+use crate::shape::csg::CSG;
+use crate::shape::volume::Volume;
+use crate::medium::Medium;
+use crate::medium::fog::Fog;
+use crate::medium::density::{Density, Homogeneous, PatternDensity, Grid};
+use crate::medium::phase::HenyeyGreenstein;
+use crate::math::aabb::Aabb;
+use crate::shape::moving::Moving;
+use crate::shape::obj::{self, ObjError};
+use crate::environment::{Environment, Constant, Sky, EnvironmentMap};
+
+type Vector = (f64, f64, f64);
+
+fn vec3(v: Vector) -> Vec3 {
+	Vec3::new(v.0, v.1, v.2)
+}
+
+// (roll, pitch, yaw), in radians
+fn rotation(v: Vector) -> DRotor3 {
+	DRotor3::from_euler_angles(v.0, v.1, v.2)
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+	Io(PathBuf, io::Error),
+	Syntax { line: usize, column: usize, message: String },
+	Mesh(PathBuf, ObjError),
+	Grid(String)
+}
+
+impl Display for LoadError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LoadError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+			LoadError::Syntax { line, column, message } => write!(f, "line {}, column {}: {}", line, column, message),
+			LoadError::Mesh(path, err) => write!(f, "mesh {}: {}", path.display(), err),
+			LoadError::Grid(message) => write!(f, "density grid: {}", message)
+		}
+	}
+}
+
+impl std::error::Error for LoadError {}
+
+/// What descriptions are built with.
+#[derive(Copy, Clone, Debug)]
+pub struct Context<'a> {
+	/// Relative texture and mesh paths are resolved against this.
+	pub base: &'a Path,
+	/// Space the colors are given in, which can be changed with `Space`.
+	pub space: ColorSpace,
+	/// Space the scene is rendered in, which all colors are converted to.
+	pub working_space: ColorSpace
+}
+
+impl Context<'_> {
+	fn conversion(&self) -> ColorMatrix {
+		self.space.conversion(self.working_space)
+	}
+
+	fn color(&self, color: Vector) -> Color {
+		self.conversion().transform(Color(color.0, color.1, color.2))
+	}
+
+	fn image(&self, image: &mut Image) {
+		let conversion = self.conversion();
+
+		for pixel in &mut image.pixels {
+			*pixel = conversion.transform(*pixel);
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum AlbedoDescription {
+	Solid(f64, f64, f64),
+	Normals,
+	/// PNG or PPM image. `encoding` is `Srgb` (the default) or `Linear`, and
+	/// `wrap` is `Repeat` (the default), `Mirror` or `Clamp`.
+	Texture {
+		path: String,
+		#[serde(default)]
+		encoding: Encoding,
+		#[serde(default)]
+		wrap: Wrap
+	},
+	/// Procedural pattern mapped onto colors by a ramp of `(value, (r, g, b))`
+	/// stops. The pattern can be moved with `position` and `rotation`.
+	Pattern {
+		pattern: PatternDescription,
+		ramp: Vec<(f64, Vector)>,
+		#[serde(default)]
+		position: Vector,
+		#[serde(default)]
+		rotation: Vector
+	},
+	/// The colors of the albedo, including textures, are in the given space
+	/// rather than Rec709.
+	Space(ColorSpace, Box<AlbedoDescription>)
+}
+
+impl AlbedoDescription {
+	pub fn build(&self, context: &Context) -> Result<Box<dyn Albedo>, LoadError> {
+		Ok(match self {
+			AlbedoDescription::Solid(r, g, b) => Box::new(Solid(context.color((*r, *g, *b)))),
+			AlbedoDescription::Normals => Box::new(Normals()),
+			AlbedoDescription::Texture { path, encoding, wrap } => {
+				let path = context.base.join(path);
+				let mut texture = ImageTexture::open(&path, *encoding, *wrap).map_err(|err| LoadError::Io(path, err))?;
+				context.image(&mut texture.image);
+
+				Box::new(texture)
+			}
+			AlbedoDescription::Pattern { pattern, ramp, position, rotation: rot } => {
+				let stops = ramp.iter().map(|(value, c)| (*value, context.color(*c))).collect();
+				let mut albedo = Procedural::new(pattern.build(), ColorRamp::new(stops));
+				albedo.transform = Pos::new(vec3(*position), rotation(*rot));
+
+				Box::new(albedo)
+			}
+			AlbedoDescription::Space(space, albedo) => albedo.build(&Context { space: *space, ..*context })?
+		})
+	}
+}
+
+fn octaves() -> u32 {
+	4
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum PatternDescription {
+	/// Size of the cubes.
+	Checker(f64),
+	Noise {
+		scale: f64,
+		#[serde(default = "octaves")]
+		octaves: u32
+	},
+	Turbulence {
+		scale: f64,
+		#[serde(default = "octaves")]
+		octaves: u32
+	},
+	Marble {
+		scale: f64,
+		distortion: f64
+	},
+	Wood {
+		rings: f64,
+		distortion: f64
+	}
+}
+
+impl PatternDescription {
+	pub fn build(&self) -> Box<dyn Pattern> {
+		match *self {
+			PatternDescription::Checker(size) => Box::new(Checker { size }),
+			PatternDescription::Noise { scale, octaves } => Box::new(Noise::new(scale, octaves)),
+			PatternDescription::Turbulence { scale, octaves } => Box::new(Turbulence::new(scale, octaves)),
+			PatternDescription::Marble { scale, distortion } => Box::new(Marble::new(scale, distortion)),
+			PatternDescription::Wood { rings, distortion } => Box::new(Wood::new(rings, distortion))
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum MaterialDescription {
+	Lambertian(AlbedoDescription),
+	Metal(AlbedoDescription, f64),
+	Glossy(AlbedoDescription),
+	Emissive(AlbedoDescription, f64),
+	/// Albedo, index of refraction and Abbe number, see `Dielectric`.
+	Dielectric(AlbedoDescription, f64, #[serde(default)] f64),
+	Mapper,
+	Portal(Vector, #[serde(default)] Vector),
+	/// Combinations of two materials, see `Composite`.
+	Add(Box<MaterialDescription>, Box<MaterialDescription>),
+	Sub(Box<MaterialDescription>, Box<MaterialDescription>),
+	Div(Box<MaterialDescription>, Box<MaterialDescription>),
+	Mul(Box<MaterialDescription>, Box<MaterialDescription>),
+	Lerp(Box<MaterialDescription>, Box<MaterialDescription>, f64)
+}
+
+impl MaterialDescription {
+	pub fn build(&self, context: &Context) -> Result<Box<dyn Material>, LoadError> {
+		Ok(match self {
+			MaterialDescription::Lambertian(albedo) => Box::new(Lambertian(albedo.build(context)?)),
+			MaterialDescription::Metal(albedo, roughness) => Box::new(Metal(albedo.build(context)?, *roughness)),
+			MaterialDescription::Glossy(albedo) => Box::new(Glossy(albedo.build(context)?)),
+			MaterialDescription::Emissive(albedo, strength) => Box::new(Emissive::new(albedo.build(context)?, *strength)),
+			MaterialDescription::Dielectric(albedo, ior, abbe) => Box::new(Dielectric::dispersive(albedo.build(context)?, *ior, *abbe)),
+			MaterialDescription::Mapper => Box::new(Mapper),
+			MaterialDescription::Portal(position, rot) => Box::new(Portal(Pos::new(vec3(*position), rotation(*rot)))),
+			MaterialDescription::Add(a, b) => Box::new(Composite::Add(a.build(context)?, b.build(context)?)),
+			MaterialDescription::Sub(a, b) => Box::new(Composite::Sub(a.build(context)?, b.build(context)?)),
+			MaterialDescription::Div(a, b) => Box::new(Composite::Div(a.build(context)?, b.build(context)?)),
+			MaterialDescription::Mul(a, b) => Box::new(Composite::Mul(a.build(context)?, b.build(context)?)),
+			MaterialDescription::Lerp(a, b, t) => Box::new(Composite::Lerp(a.build(context)?, b.build(context)?, *t))
+		})
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum ShapeDescription {
+	Sphere {
+		center: Vector,
+		radius: f64,
+		material: MaterialDescription
+	},
+	Plane {
+		center: Vector,
+		normal: Vector,
+		material: MaterialDescription
+	},
+	FinitePlane {
+		position: Vector,
+		#[serde(default)]
+		rotation: Vector,
+		size: (f64, f64),
+		material: MaterialDescription
+	},
+	Triangle {
+		vertices: (Vector, Vector, Vector),
+		material: MaterialDescription
+	},
+	/// Wavefront OBJ file. Every `usemtl` or group name is looked up in
+	/// `materials`, and `material` is used for names that aren't in there.
+	Mesh {
+		path: String,
+		material: MaterialDescription,
+		#[serde(default)]
+		materials: HashMap<String, MaterialDescription>
+	},
+	Union(Box<ShapeDescription>, Box<ShapeDescription>),
+	Subtract(Box<ShapeDescription>, Box<ShapeDescription>),
+	Intersection(Box<ShapeDescription>, Box<ShapeDescription>),
+	/// The shape filled with a medium. The material of the shape isn't used.
+	Volume(Box<ShapeDescription>, MediumDescription),
+	/// Moves the shape by the vector over the course of the exposure.
+	Moving(Box<ShapeDescription>, Vector),
+	/// Puts the shapes in a `Bvh`, which is useful for big groups of objects.
+	Bvh(Vec<ShapeDescription>)
+}
+
+impl ShapeDescription {
+	pub fn build(&self, context: &Context) -> Result<Box<dyn Hittable>, LoadError> {
+		Ok(match self {
+			ShapeDescription::Sphere { center, radius, material } => Box::new(Sphere {
+				center: vec3(*center),
+				radius: *radius,
+				material: material.build(context)?
+			}),
+			ShapeDescription::Plane { center, normal, material } => Box::new(Plane {
+				center: vec3(*center),
+				normal: vec3(*normal).normalized(),
+				material: material.build(context)?
+			}),
+			ShapeDescription::FinitePlane { position, rotation: rot, size, material } => Box::new(FinitePlane {
+				pos: Pos::new(vec3(*position), rotation(*rot)),
+				size: *size,
+				material: material.build(context)?
+			}),
+			ShapeDescription::Triangle { vertices: (a, b, c), material } => Box::new(Triangle {
+				vertices: [vec3(*a), vec3(*b), vec3(*c)],
+				material: material.build(context)?
+			}),
+			ShapeDescription::Mesh { path, material, materials } => {
+				let path = context.base.join(path);
+
+				// the OBJ loader can't fail because of a material, so remember the
+				// first error and fall back to black until it's done
+				let mut material_error = None;
+
+				let mesh = obj::open(&path, |name| {
+					materials.get(name).unwrap_or(material).build(context).unwrap_or_else(|err| {
+						material_error.get_or_insert(err);
+						Box::new(Lambertian::solid(Color::default()))
+					})
+				}).map_err(|err| match err {
+					ObjError::Io(err) => LoadError::Io(path.clone(), err),
+					err => LoadError::Mesh(path.clone(), err)
+				})?;
+
+				if let Some(err) = material_error {
+					return Err(err)
+				}
+
+				Box::new(mesh)
+			}
+			ShapeDescription::Union(a, b) => Box::new(CSG::union(a.build(context)?, b.build(context)?)),
+			ShapeDescription::Subtract(a, b) => Box::new(CSG::subtract(a.build(context)?, b.build(context)?)),
+			ShapeDescription::Intersection(a, b) => Box::new(CSG::intersection(a.build(context)?, b.build(context)?)),
+			ShapeDescription::Volume(shape, medium) => Box::new(Volume(shape.build(context)?, medium.build(context)?)),
+			ShapeDescription::Moving(shape, velocity) => Box::new(Moving(shape.build(context)?, vec3(*velocity))),
+			ShapeDescription::Bvh(shapes) => Box::new(Bvh::new(
+				shapes.iter().map(|s| s.build(context)).collect::<Result<_, _>>()?
+			))
+		})
+	}
+}
+
+fn one() -> f64 {
+	1.
+}
+
+/// What a `Volume` is filled with. The coefficients are colors, per unit of
+/// distance at a density of 1.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MediumDescription {
+	#[serde(default)]
+	pub absorption: Vector,
+	#[serde(default)]
+	pub scattering: Vector,
+	/// Henyey-Greenstein `g`, from -1 for scattering backwards to 1 for
+	/// scattering forwards.
+	#[serde(default)]
+	pub anisotropy: f64,
+	#[serde(default)]
+	pub density: DensityDescription
+}
+
+// converting saturated colors can make them a little negative
+fn coefficient(context: &Context, c: Vector) -> Color {
+	let color = context.color(c);
+	Color(color.0.max(0.), color.1.max(0.), color.2.max(0.))
+}
+
+impl MediumDescription {
+	pub fn build(&self, context: &Context) -> Result<Medium<Box<dyn Density>>, LoadError> {
+		Ok(Medium {
+			absorption: coefficient(context, self.absorption),
+			scattering: coefficient(context, self.scattering),
+			phase: HenyeyGreenstein::new(self.anisotropy),
+			density: self.density.build()?
+		})
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum DensityDescription {
+	Homogeneous(f64),
+	/// Pattern values above `threshold` stretched to go from 0 to `density`.
+	/// The pattern can be moved with `position` and `rotation`.
+	Pattern {
+		pattern: PatternDescription,
+		#[serde(default)]
+		threshold: f64,
+		#[serde(default = "one")]
+		density: f64,
+		#[serde(default)]
+		position: Vector,
+		#[serde(default)]
+		rotation: Vector
+	},
+	/// Densities on a grid of `resolution` points stretched from `min` to
+	/// `max`, with X changing fastest, then Y, then Z.
+	Grid {
+		min: Vector,
+		max: Vector,
+		resolution: (usize, usize, usize),
+		values: Vec<f64>
+	}
+}
+
+impl Default for DensityDescription {
+	fn default() -> Self {
+		DensityDescription::Homogeneous(1.)
+	}
+}
+
+impl DensityDescription {
+	pub fn build(&self) -> Result<Box<dyn Density>, LoadError> {
+		Ok(match self {
+			DensityDescription::Homogeneous(density) => Box::new(Homogeneous(*density)),
+			DensityDescription::Pattern { pattern, threshold, density, position, rotation: rot } => {
+				let mut density = PatternDensity::new(pattern.build(), *threshold, *density);
+				density.transform = Pos::new(vec3(*position), rotation(*rot));
+
+				Box::new(density)
+			}
+			DensityDescription::Grid { min, max, resolution: (x, y, z), values } => {
+				let grid = Grid::new(Aabb::new(vec3(*min), vec3(*max)), [*x, *y, *z], values.clone());
+
+				Box::new(grid.ok_or_else(|| LoadError::Grid(format!(
+					"a resolution of {:?} needs at least 2 points along every axis and {} values, but there are {}",
+					(x, y, z), x * y * z, values.len()
+				)))?)
+			}
+		})
+	}
+}
+
+/// Medium filling the whole scene. The coefficients are like a
+/// `MediumDescription`'s, at `height`, and thin out going up by a factor of e
+/// every 1 / `falloff`. A `falloff` of 0 makes it the same everywhere.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FogDescription {
+	#[serde(default)]
+	pub absorption: Vector,
+	#[serde(default)]
+	pub scattering: Vector,
+	#[serde(default)]
+	pub anisotropy: f64,
+	#[serde(default)]
+	pub height: f64,
+	#[serde(default)]
+	pub falloff: f64
+}
+
+impl FogDescription {
+	pub fn build(&self, context: &Context) -> Fog {
+		let mut medium = Medium::homogeneous(coefficient(context, self.absorption), coefficient(context, self.scattering));
+		medium.phase = HenyeyGreenstein::new(self.anisotropy);
+
+		Fog { medium, height: self.height, falloff: self.falloff }
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum EnvironmentDescription {
+	Constant(f64, f64, f64),
+	/// The sky from Ray Tracing in One Weekend.
+	Sky,
+	/// Gradient from the horizon color to the zenith color.
+	Gradient(Vector, Vector),
+	/// Equirectangular `.hdr` or `.pfm` image.
+	Map {
+		path: String,
+		#[serde(default)]
+		rotation: Vector,
+		#[serde(default = "one")]
+		intensity: f64
+	},
+	/// The colors of the environment are in the given space rather than
+	/// Rec709.
+	Space(ColorSpace, Box<EnvironmentDescription>)
+}
+
+impl Default for EnvironmentDescription {
+	fn default() -> Self {
+		EnvironmentDescription::Constant(0., 0., 0.)
+	}
+}
+
+impl EnvironmentDescription {
+	pub fn build(&self, context: &Context) -> Result<Box<dyn Environment>, LoadError> {
+		Ok(match self {
+			EnvironmentDescription::Constant(r, g, b) => Box::new(Constant(context.color((*r, *g, *b)))),
+			EnvironmentDescription::Sky => {
+				let sky = Sky::classic();
+				let conversion = context.conversion();
+
+				Box::new(Sky { horizon: conversion.transform(sky.horizon), zenith: conversion.transform(sky.zenith) })
+			}
+			EnvironmentDescription::Gradient(horizon, zenith) => Box::new(Sky {
+				horizon: context.color(*horizon),
+				zenith: context.color(*zenith)
+			}),
+			EnvironmentDescription::Map { path, rotation: rot, intensity } => {
+				let path = context.base.join(path);
+				let mut map = EnvironmentMap::open(&path).map_err(|err| LoadError::Io(path, err))?;
+
+				context.image(&mut map.image);
+				map.rotation = rotation(*rot);
+				map.intensity = *intensity;
+				Box::new(map)
+			}
+			EnvironmentDescription::Space(space, environment) => environment.build(&Context { space: *space, ..*context })?
+		})
+	}
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(default, rename = "Camera")]
+pub struct CameraDescription {
+	pub position: Vector,
+	pub yaw: f64,
+	pub pitch: f64,
+	/// `Vertical(degrees)`, `Horizontal(degrees)` or `Shorter(degrees)`.
+	pub fov: Fov,
+	pub aperture: f64,
+	pub focus_distance: f64,
+	/// Where the camera ends up at the end of the exposure. Anything that isn't
+	/// given stays the same.
+	pub end_position: Option<Vector>,
+	pub end_yaw: Option<f64>,
+	pub end_pitch: Option<f64>,
+	pub shutter: (f64, f64)
+}
+
+impl Default for CameraDescription {
+	fn default() -> Self {
+		CameraDescription {
+			position: (0., 1., 10.),
+			yaw: 0.,
+			pitch: 0.,
+			fov: Fov::default(),
+			aperture: 0.,
+			focus_distance: 10.,
+			end_position: None,
+			end_yaw: None,
+			end_pitch: None,
+			shutter: (0., 1.)
+		}
+	}
+}
+
+impl CameraDescription {
+	pub fn build(&self) -> Camera {
+		let mut camera = Camera::new(Pos::new(vec3(self.position), DRotor3::from_euler_angles(0., self.pitch, self.yaw)));
+
+		camera.fov = self.fov;
+		camera.aperture = self.aperture;
+		camera.focus_distance = self.focus_distance;
+		camera.shutter = self.shutter;
+
+		if self.end_position.is_some() || self.end_yaw.is_some() || self.end_pitch.is_some() {
+			let pitch = self.end_pitch.unwrap_or(self.pitch);
+			let yaw = self.end_yaw.unwrap_or(self.yaw);
+
+			camera.blur_pos = Some(Pos::new(
+				vec3(self.end_position.unwrap_or(self.position)),
+				DRotor3::from_euler_angles(0., pitch, yaw)
+			));
+		}
+
+		camera
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename = "Scene")]
+pub struct SceneDescription {
+	#[serde(default)]
+	pub camera: CameraDescription,
+	#[serde(default)]
+	pub environment: EnvironmentDescription,
+	/// Space the scene is rendered in. Colors are given in Rec709 unless they
+	/// say otherwise, and are converted to this.
+	#[serde(default)]
+	pub working_space: ColorSpace,
+	#[serde(default)]
+	pub fog: Option<FogDescription>,
+	pub objects: Vec<ShapeDescription>
+}
+
+// ron doesn't know where errors raised by serde itself (like missing fields)
+// happened, so work it out from how much of the input is left
+fn position(source: &str, offset: usize) -> (usize, usize) {
+	let before = &source[..offset.min(source.len())];
+	let line = before.matches('\n').count() + 1;
+	let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+
+	(line, column)
+}
+
+impl SceneDescription {
+	pub fn parse(source: &str) -> Result<Self, LoadError> {
+		let mut deserializer = ron::de::Deserializer::from_str(source).map_err(|err| LoadError::Syntax {
+			line: err.position.line,
+			column: err.position.col,
+			message: err.code.to_string()
+		})?;
+
+		let result = SceneDescription::deserialize(&mut deserializer)
+			.and_then(|description| deserializer.end().map(|_| description));
+
+		result.map_err(|err| {
+			let (line, column) = if err.position.line == 0 {
+				position(source, source.len() - deserializer.remainder().len())
+			} else {
+				(err.position.line, err.position.col)
+			};
+
+			LoadError::Syntax { line, column, message: err.code.to_string() }
+		})
+	}
+
+	/// Relative paths are resolved against `base`.
+	pub fn build(&self, base: &Path) -> Result<TestScene, LoadError> {
+		let context = &Context { base, space: ColorSpace::Rec709, working_space: self.working_space };
+
+		let mut scene = TestScene::new();
+		scene.working_space = self.working_space;
+
+		for object in &self.objects {
+			scene.objects.push(object.build(context)?);
+		}
+
+		scene.environment = self.environment.build(context)?;
+		scene.fog = self.fog.as_ref().map(|fog| fog.build(context));
+
+		Ok(scene)
+	}
+}
+
+/// Loads the scene at `path` and returns it along with the camera pose.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<(TestScene, CameraDescription), LoadError> {
+	let path = path.as_ref();
+	let source = std::fs::read_to_string(path).map_err(|err| LoadError::Io(path.to_path_buf(), err))?;
+	let description = SceneDescription::parse(&source)?;
+	let scene = description.build(path.parent().unwrap_or(Path::new("")))?;
+
+	Ok((scene, description.camera))
+}
@@ -1,26 +1,112 @@
 use minifb::{Window, WindowOptions, Key, KeyRepeat};
 use std::time::Instant;
-use in_one_weekend::math::color::Color;
-use in_one_weekend::math::space::{Pos, Vec3};
+use std::sync::Arc;
+use std::path::Path;
+use in_one_weekend::math::space::Vec3;
 use ultraviolet::DRotor3;
 use in_one_weekend::scene::TestScene;
+use in_one_weekend::loader::{self, CameraDescription};
 use in_one_weekend::camera::Camera;
 use in_one_weekend::progressive::ProgressiveBuffer;
-
-use in_one_weekend::shape::sphere::Sphere;
-use in_one_weekend::shape::plane::{Plane, FinitePlane};
-use in_one_weekend::shape::csg::CSG;
-use in_one_weekend::material::lambertian::Lambertian;
-use in_one_weekend::material::glossy::Glossy;
-use in_one_weekend::material::albedo::normals::Normals;
-use in_one_weekend::material::emissive::Emissive;
+use in_one_weekend::aov::{Aov, Aovs};
+use in_one_weekend::checkpoint::{self, Checkpoint, Settings};
+use in_one_weekend::math::color::{Color, ToneMapper};
+use in_one_weekend::math::color_space::ColorSpace;
+use in_one_weekend::math::spectrum::Spectral;
+use in_one_weekend::integrator::PathTracer;
+use in_one_weekend::rng::{Sampler, Independent, Halton, Sobol, BlueNoise};
 
 #[cfg(not(debug_assertions))]
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use in_one_weekend::shape::volume::Volume;
+
+const DEFAULT_CHECKPOINT: &str = "render.checkpoint";
 
 fn main() {
-	let mut window = Window::new("Thing", 640, 480,
+	let mut scene_path = None;
+	let mut checkpoint_path = None;
+	let mut args = std::env::args().skip(1);
+
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--checkpoint" => checkpoint_path = args.next(),
+			_ => scene_path = Some(arg)
+		}
+	}
+
+	let (mut scene, mut pose) = match &scene_path {
+		Some(path) => loader::load(path).unwrap_or_else(|err| {
+			eprintln!("error: couldn't load {}: {}", path, err);
+			std::process::exit(1)
+		}),
+		None => (TestScene::example(), CameraDescription::default())
+	};
+
+	// the scene was just loaded from the file, so it can be read again
+	let source = scene_path.as_ref().map_or(String::new(), |path| std::fs::read_to_string(path).unwrap_or_default());
+
+	scene.build_bvh();
+	scene.collect_lights();
+	scene.collect_materials();
+
+	// stratified sampling needs to know the sample count up front, which the
+	// viewer doesn't, so it's left out
+	let samplers: [(&str, Arc<dyn Sampler>); 4] = [
+		("independent", Arc::new(Independent)),
+		("halton", Arc::new(Halton)),
+		("sobol", Arc::new(Sobol::new())),
+		("blue-noise", Arc::new(BlueNoise::new()))
+	];
+
+	let mut sampler_index = 0;
+
+	let mut integrator = PathTracer::default();
+	// fitting the spectra takes a moment, so it's only done once
+	let mut spectral = None;
+
+	// an existing checkpoint is picked up where it was left, camera included
+	let checkpoint = match &checkpoint_path {
+		Some(path) if Path::new(path).exists() => {
+			let checkpoint = Checkpoint::load(path).unwrap_or_else(|err| {
+				eprintln!("error: couldn't load {}: {}", path, err);
+				std::process::exit(1)
+			});
+
+			pose = checkpoint.camera;
+			let camera = pose.build();
+
+			// neither the sampler nor whether tracing was spectral is saved, but
+			// only one of each matches
+			let matching = |integrator: &PathTracer| samplers.iter().position(|&(name, _)| {
+				checkpoint.matches(&Settings { scene: &source, camera: &pose, sampler: name, seed: camera.seed, integrator })
+			});
+
+			let index = matching(&integrator).or_else(|| {
+				let spectral = spectral.get_or_insert_with(|| Arc::new(Spectral::new(scene.working_space))).clone();
+				let spectral_integrator = PathTracer { spectral: Some(spectral), ..integrator.clone() };
+				let index = matching(&spectral_integrator)?;
+
+				integrator = spectral_integrator;
+				Some(index)
+			});
+
+			match index {
+				Some(index) => sampler_index = index,
+				None => {
+					eprintln!("error: can't resume from {}: it's of a different scene, camera, sampler or tracing mode", path);
+					std::process::exit(1)
+				}
+			}
+
+			println!("resuming from {} at {} spp, tracing: {}", path, checkpoint.progressive.spp(), if integrator.spectral.is_some() { "spectral" } else { "rgb" });
+			Some(checkpoint.progressive)
+		}
+		_ => None
+	};
+
+	// at a pixel scale of 2 the window is as big as the checkpoint
+	let (window_width, window_height) = checkpoint.as_ref().map_or((640, 480), |p| (p.width(), p.height()));
+
+	let mut window = Window::new("Thing", window_width, window_height,
 		WindowOptions { resize: true, ..WindowOptions::default() }
 	).expect("Couldn't create window");
 
@@ -30,163 +116,11 @@ fn main() {
 	window.limit_update_rate(None);
 	window.update();
 
-	let mut scene = TestScene::new();
-
-	scene.objects.push(Box::new(Plane {
-		center: Vec3::new(0., 0., 0.),
-		normal: Vec3::new(0., 1., 0.),
-		material: Box::new(Lambertian::solid(Color(0.1, 0.1, 0.1)))
-	}));
-
-	//scene.objects.push(Box::new(Sphere {
-	//	center: Vec3::new(-2., 1., 0.),
-	//	radius: 1.,
-	//	material: Box::new(Lambertian::solid(Color(1., 1., 1.)))
-	//}));
-
-	//scene.objects.push(Box::new(Sphere {
-	//	center: Vec3::new(0., 1., 0.),
-	//	radius: 1.,
-	//	material: Box::new(Normals())
-	//}));
-
-	//scene.objects.push(Box::new(Sphere {
-	//	center: Vec3::new(2., 1., 0.),
-	//	radius: 1.,
-	//	material: Box::new(Mirror(0.))
-	//}));
-
-	//scene.objects.push(Box::new(Plane {
-	//	center: Vec3::new(-15., 0., 0.),
-	//	normal: Vec3::new(1., 0., 0.),
-	//	material: Box::new(Solid(Color(0.9, 0.25, 0.25)))
-	//}));
-
-	//scene.objects.push(Box::new(Plane {
-	//	center: Vec3::new(15., 0., 0.),
-	//	normal: Vec3::new(-1., 0., 0.),
-	//	material: Box::new(Solid(Color(0.25, 0.25, 0.9)))
-	//}));
-
-	//scene.objects.push(Box::new(FinitePlane {
-	//	pos: Pos::new(Vec3::new(-4., 1., 0.), DRotor3::from_euler_angles(0., PI / 2., 0.)),
-	//	size: (1., 2.),
-	//	material: Box::new(Portal(Pos::new(Vec3::new(4., 0., 2.), DRotor3::identity())))
-	//}));
-
-	//for _ in 0..500 {
-	//	scene.objects.push(Box::new(Sphere {
-	//		center: Vec3::new(
-	//			rand::thread_rng().gen_range(-15., 15.),
-	//			0.5,
-	//			rand::thread_rng().gen_range(-15., 15.)
-	//		),
-	//		radius: 0.5,
-	//		material: Box::new(Normals {})
-	//	}));
-	//}
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(-3.75, 0.5, 0.),
-		radius: 0.5,
-		material: Box::new(Lambertian::solid(Color(0.25, 0.25, 1.0)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(-2.25, 0.5, 0.),
-		radius: 0.5,
-		material: Box::new(Emissive::solid(Color::splat(5.)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(-0.75, 0.5, 0.),
-		radius: 0.5,
-		material: Box::new(Lambertian::solid(Color(1.0, 0.25, 0.25)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(0.75, 0.5, 0.),
-		radius: 0.5,
-		material: Box::new(Emissive::solid(Color::splat(5.)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(2.25, 0.5, 0.),
-		radius: 0.5,
-		material: Box::new(Lambertian::solid(Color(0.25, 1.0, 0.25)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(3.75, 0.5, 0.),
-		radius: 0.5,
-		material: Box::new(Emissive::solid(Color::splat(5.)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(0., 2., 0.),
-		radius: 0.5,
-		material: Box::new(Glossy(Normals()))
-	}));
-
-	let csg1 = Sphere {
-		center: Vec3::new(-3., 2., 0.),
-		radius: 0.5,
-		material: Box::new(Lambertian(Normals()))
-	};
-
-	let csg2 = Sphere {
-		center: Vec3::new(-2.5, 2.5, 0.),
-		radius: 0.5,
-		material: Box::new(Lambertian::solid(Color::splat(0.75)))
-	};
-
-	let csghalve = Plane {
-		center: Vec3::new(-3., 2., 0.),
-		normal: Vec3::new(0., 0., 1.),
-		material: Box::new(Lambertian::solid(Color::splat(0.5)))
-	};
-
-	scene.objects.push(Box::new(CSG::subtract(
-		CSG::union(csg1, csg2),
-		csghalve
-	)));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(-3., 2., 0.),
-		radius: 0.125,
-		material: Box::new(Emissive::solid(Color::splat(5.)))
-	}));
-
-	scene.objects.push(Box::new(Sphere {
-		center: Vec3::new(-2.5, 2.5, 0.),
-		radius: 0.125,
-		material: Box::new(Emissive::solid(Color::splat(5.)))
-	}));
-
-	scene.objects.push(Box::new(Volume(Sphere {
-		center: Vec3::new(0., 4., 0.),
-		radius: 0.5,
-		material: Box::new(Lambertian::solid(Color::splat(1.)))
-	}, 1.)));
-
-	//scene.objects.push(Box::new(FinitePlane {
-	//	pos: Pos::new(Vec3::new(0., 5., 0.), DRotor3::identity()),
-	//	size: (10., 5.),
-	//	material: Box::new(Solid(Color(1.0, 1.0, 1.0)))
-	//}));
-
-	scene.objects.push(Box::new(FinitePlane {
-		pos: Pos::new(Vec3::new(0., 15., 0.), DRotor3::identity()),
-		size: (10., 5.),
-		material: Box::new(Emissive::solid(Color::splat(5.)))
-	}));
-
-	let mut camera_yaw = 0.;
-	let mut camera_pitch = 0.;
-	let mut camera = Camera {
-		pos: Pos::new(Vec3::new(0., 1., 10.), DRotor3::identity()),
-		blur_pos: None
-	};
+	let mut camera_yaw = pose.yaw;
+	let mut camera_pitch = pose.pitch;
+	let mut camera = pose.build();
+	camera.sampler = samplers[sampler_index].1.clone();
+	camera.integrator = Box::new(integrator.clone());
 
 	const DEFAULT_DIVIDE: usize = 8;
 	const ULTRA_DIVIDE: usize = 1;
@@ -197,18 +131,36 @@ fn main() {
 	#[cfg(not(target_os = "macos"))]
 	const MIN_DIVIDE: usize = 2;
 
-	let mut divide = DEFAULT_DIVIDE;
-	let mut progressive: Option<ProgressiveBuffer> = Some(ProgressiveBuffer::new(0, 0));
+	let mut divide = if checkpoint.is_some() { 2 } else { DEFAULT_DIVIDE };
+
+	// the denoiser needs the albedo and normals anyway, and the rest are cheap
+	let aovs = checkpoint.as_ref().map_or_else(Aovs::all, |p| p.frame().aovs().clone());
+
+	let mut progressive: Option<ProgressiveBuffer> = Some(checkpoint.unwrap_or_else(|| ProgressiveBuffer::new(0, 0, aovs.clone())));
 
 	const DEFAULT_EXPOSURE: f64 = 1.;
 
 	let mut exposure_correction = DEFAULT_EXPOSURE;
 
+	// the window is assumed to be sRGB
+	let display = scene.working_space.conversion(ColorSpace::Rec709);
+
+	let mut tone_mapper = ToneMapper::default();
+	let mut white_point = tone_mapper.default_white_point();
+
 	let mut denoising = false;
+
+	// `None` shows the color
+	let mut viewed_aov: Option<Aov> = None;
+
 	let device = oidn::Device::new();
 
 	let mut last = Instant::now();
 
+	// without progressive rendering every frame is a new sample, so that the
+	// noise doesn't freeze in place
+	let mut frame_index = 0;
+
 	while window.is_open() {
 		let lastlast = last;
 		last = Instant::now();
@@ -294,6 +246,21 @@ fn main() {
 			exposure_correction = DEFAULT_EXPOSURE;
 		}
 
+		if window.is_key_down(Key::Minus) {
+			white_point /= f64::powf(3., diff);
+		}
+
+		if window.is_key_down(Key::Equal) {
+			white_point *= f64::powf(3., diff);
+		}
+
+		if window.is_key_pressed(Key::T, KeyRepeat::No) {
+			let index = ToneMapper::ALL.iter().position(|&t| t == tone_mapper).unwrap();
+			tone_mapper = ToneMapper::ALL[(index + 1) % ToneMapper::ALL.len()];
+			white_point = tone_mapper.default_white_point();
+			println!("tone mapper: {}", tone_mapper.name());
+		}
+
 		if window.is_key_pressed(Key::Comma, KeyRepeat::No) {
 			divide *= 2;
 			refreshing_progressive = true;
@@ -325,15 +292,65 @@ fn main() {
 					progressive = None
 				}
 				None => {
-					progressive = Some(ProgressiveBuffer::new(width, height))
+					progressive = Some(ProgressiveBuffer::new(width, height, aovs.clone()))
 				}
 			}
-		} else if progressive.is_some() && (refreshing_progressive || progressive.as_ref().unwrap().as_ref().len() != width * height) {
-			progressive = Some(ProgressiveBuffer::new(width, height))
+		} else if progressive.is_some() && (refreshing_progressive || progressive.as_ref().unwrap().frame().len() != width * height) {
+			progressive = Some(ProgressiveBuffer::new(width, height, aovs.clone()))
+		}
+
+		if window.is_key_pressed(Key::N, KeyRepeat::No) {
+			sampler_index = (sampler_index + 1) % samplers.len();
+			camera.sampler = samplers[sampler_index].1.clone();
+			println!("sampler: {}", samplers[sampler_index].0);
+
+			if progressive.is_some() {
+				progressive = Some(ProgressiveBuffer::new(width, height, aovs.clone()))
+			}
+		}
+
+		if window.is_key_pressed(Key::L, KeyRepeat::No) {
+			integrator.spectral = match integrator.spectral {
+				Some(_) => None,
+				None => Some(spectral.get_or_insert_with(|| Arc::new(Spectral::new(scene.working_space))).clone())
+			};
+
+			camera.integrator = Box::new(integrator.clone());
+			println!("tracing: {}", if integrator.spectral.is_some() { "spectral" } else { "rgb" });
+
+			if progressive.is_some() {
+				progressive = Some(ProgressiveBuffer::new(width, height, aovs.clone()))
+			}
 		}
 
 		if window.is_key_pressed(Key::O, KeyRepeat::No) {
-			denoising = !denoising;
+			if aovs.contains(Aov::Albedo) && aovs.contains(Aov::Normal) {
+				denoising = !denoising;
+			} else {
+				println!("denoising needs the albedo and normals, which the checkpoint doesn't have");
+			}
+		}
+
+		if window.is_key_pressed(Key::V, KeyRepeat::No) {
+			let list = aovs.list();
+
+			viewed_aov = match viewed_aov {
+				None => list.first().copied(),
+				Some(aov) => list.iter().position(|&a| a == aov).and_then(|i| list.get(i + 1)).copied()
+			};
+
+			println!("viewing: {}", viewed_aov.map_or("color", Aov::name));
+		}
+
+		if window.is_key_pressed(Key::K, KeyRepeat::No) {
+			match &progressive {
+				Some(progressive) => {
+					let path = checkpoint_path.as_deref().unwrap_or(DEFAULT_CHECKPOINT);
+					let pose = viewer_pose(&pose, &camera, camera_yaw, camera_pitch);
+					save_checkpoint(path, &Settings { scene: &source, camera: &pose, sampler: samplers[sampler_index].0, seed: camera.seed, integrator: &integrator }, progressive);
+				}
+				None => println!("checkpoints need progressive mode")
+			}
 		}
 
 		let render;
@@ -347,7 +364,7 @@ fn main() {
 				loop {
 					let render_start = Instant::now();
 
-					progressive.update(&camera.render_combined(&scene, width, height));
+					progressive.update(&camera.render(&scene, width, height, progressive.spp(), &aovs));
 					num += 1;
 
 					let now = Instant::now();
@@ -362,18 +379,28 @@ fn main() {
 				println!("samples per frame: {}", num * width * height);
 				println!("samples per second: {}", ((num * width * height) as f64 / diff) as usize);
 
-				(*progressive).as_ref()
+				progressive.frame()
 			}
 			None => {
-				render = camera.render_combined(&scene, width, height);
+				render = camera.render(&scene, width, height, frame_index, &aovs);
+				frame_index += 1;
 				&render
 			}
 		};
 
+		if let Some(aov) = viewed_aov {
+			let buffer: Vec<u32> = buf.visualize(aov).unwrap().iter().map(Color::to_srgb).collect();
+
+			window.update_with_buffer(buffer.as_slice(), width, height)
+				.expect("Couldn't update window");
+
+			continue
+		}
+
 		let buf = if denoising {
-			let mut filtered = Camera::separate_color_f32(&Camera::separate_color(buf));
-			let albedo = Camera::separate_color_f32(&Camera::separate_albedo(buf));
-			let normals = Camera::separate_vec3_f32(&Camera::separate_normals(buf));
+			let mut filtered = Camera::separate_color_f32(&buf.colors());
+			let albedo = Camera::separate_color_f32(&buf.aov_colors(Aov::Albedo).unwrap());
+			let normals = Camera::separate_vec3_f32(&buf.aov_vectors(Aov::Normal).unwrap());
 
 			oidn::RayTracing::new(&device)
 				.hdr(true)
@@ -384,7 +411,7 @@ fn main() {
 
 			Camera::unite_f32_color(&filtered)
 		} else {
-			Camera::separate_color(buf)
+			buf.colors()
 		};
 
 		#[cfg(not(debug_assertions))]
@@ -394,10 +421,28 @@ fn main() {
 			let iter = buf.into_iter();
 
 		let buffer: Vec<u32> = iter
-			.map(|c| (c * exposure_correction).tone_map_filmic_hejl2015(1.0).to_srgb())
+			.map(|c| display.transform(c * exposure_correction).tone_map(tone_mapper, white_point).to_srgb())
 			.collect();
 
 		window.update_with_buffer(buffer.as_slice(), width, height)
 			.expect("Couldn't update window");
 	}
+	// closing the window shouldn't lose the render
+	if let (Some(path), Some(progressive)) = (&checkpoint_path, &progressive) {
+		let pose = viewer_pose(&pose, &camera, camera_yaw, camera_pitch);
+		save_checkpoint(path, &Settings { scene: &source, camera: &pose, sampler: samplers[sampler_index].0, seed: camera.seed, integrator: &integrator }, progressive);
+	}
+}
+
+// `pose` moved to wherever the camera has been flown to
+fn viewer_pose(pose: &CameraDescription, camera: &Camera, yaw: f64, pitch: f64) -> CameraDescription {
+	let position = camera.pos.translation;
+	CameraDescription { position: (position.x, position.y, position.z), yaw, pitch, ..*pose }
+}
+
+fn save_checkpoint(path: &str, settings: &Settings, progressive: &ProgressiveBuffer) {
+	match checkpoint::save(path, settings, progressive) {
+		Ok(()) => println!("saved {} at {} spp", path, progressive.spp()),
+		Err(err) => eprintln!("error: couldn't write {}: {}", path, err)
+	}
 }
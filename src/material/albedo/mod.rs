@@ -4,7 +4,16 @@ use crate::math::color::Color;
 
 pub mod normals;
 pub mod solid;
+pub mod texture;
+pub mod pattern;
+pub mod procedural;
 
 pub trait Albedo: Send + Sync + Debug {
 	fn albedo(&self, result: HitResult) -> Color;
 }
+
+impl Albedo for Box<dyn Albedo> {
+	fn albedo(&self, result: HitResult) -> Color {
+		self.as_ref().albedo(result)
+	}
+}
@@ -0,0 +1,117 @@
+use std::fmt::Debug;
+use std::f64::consts::PI;
+use crate::math::space::Vec3;
+use crate::math::noise::Perlin;
+
+/// Scalar field used by `Procedural` albedos, usually between 0 and 1.
+pub trait Pattern: Send + Sync + Debug {
+	fn value(&self, point: Vec3) -> f64;
+}
+
+impl Pattern for Box<dyn Pattern> {
+	fn value(&self, point: Vec3) -> f64 {
+		self.as_ref().value(point)
+	}
+}
+
+/// 3D checkerboard of cubes with sides of `size`, alternating between 0 and 1.
+#[derive(Copy, Clone, Debug)]
+pub struct Checker {
+	pub size: f64
+}
+
+impl Pattern for Checker {
+	fn value(&self, point: Vec3) -> f64 {
+		// nudged so that flat surfaces lying on a cell boundary, like a ground
+		// plane at 0, don't flicker between two cells due to rounding
+		let cell = |c: f64| (c / self.size + 1e-6).floor() as i64;
+		((cell(point.x) + cell(point.y) + cell(point.z)) & 1) as f64
+	}
+}
+
+/// Perlin noise, remapped to between 0 and 1. `scale` is the number of noise
+/// cells per unit, and more than one octave gives fractal Brownian motion.
+#[derive(Clone, Debug)]
+pub struct Noise {
+	pub perlin: Perlin,
+	pub scale: f64,
+	pub octaves: u32
+}
+
+impl Noise {
+	pub fn new(scale: f64, octaves: u32) -> Self {
+		Noise { perlin: Perlin::default(), scale, octaves }
+	}
+}
+
+impl Pattern for Noise {
+	fn value(&self, point: Vec3) -> f64 {
+		0.5 * (self.perlin.fbm(point * self.scale, self.octaves) + 1.)
+	}
+}
+
+/// Turbulent noise with sharp creases, between 0 and 1.
+#[derive(Clone, Debug)]
+pub struct Turbulence {
+	pub perlin: Perlin,
+	pub scale: f64,
+	pub octaves: u32
+}
+
+impl Turbulence {
+	pub fn new(scale: f64, octaves: u32) -> Self {
+		Turbulence { perlin: Perlin::default(), scale, octaves }
+	}
+}
+
+impl Pattern for Turbulence {
+	fn value(&self, point: Vec3) -> f64 {
+		self.perlin.turbulence(point * self.scale, self.octaves)
+	}
+}
+
+/// Veins along the X axis, `scale` per unit, distorted by turbulence.
+#[derive(Clone, Debug)]
+pub struct Marble {
+	pub turbulence: Turbulence,
+	pub scale: f64,
+	/// How far the veins are pushed around by the turbulence.
+	pub distortion: f64
+}
+
+impl Marble {
+	pub fn new(scale: f64, distortion: f64) -> Self {
+		Marble { turbulence: Turbulence::new(scale, 6), scale, distortion }
+	}
+}
+
+impl Pattern for Marble {
+	fn value(&self, point: Vec3) -> f64 {
+		let phase = point.x * self.scale + self.distortion * self.turbulence.value(point);
+		0.5 * (1. + (phase * PI).sin())
+	}
+}
+
+/// Rings around the Y axis, `rings` per unit, distorted by noise. Each ring goes
+/// from 0 to 1.
+#[derive(Clone, Debug)]
+pub struct Wood {
+	pub noise: Noise,
+	pub rings: f64,
+	pub distortion: f64
+}
+
+impl Wood {
+	pub fn new(rings: f64, distortion: f64) -> Self {
+		Wood { noise: Noise::new(1., 3), rings, distortion }
+	}
+}
+
+impl Pattern for Wood {
+	fn value(&self, point: Vec3) -> f64 {
+		let radius = (point.x * point.x + point.z * point.z).sqrt();
+		let rings = radius * self.rings + self.distortion * (self.noise.value(point) - 0.5);
+
+		rings - rings.floor()
+	}
+}
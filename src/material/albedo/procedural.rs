@@ -0,0 +1,63 @@
+use crate::math::hit::HitResult;
+use crate::math::color::Color;
+use crate::math::space::Pos;
+use crate::material::albedo::Albedo;
+use crate::material::albedo::pattern::Pattern;
+
+/// Maps numbers onto colors by interpolating linearly between stops. Values
+/// outside of the stops get the color of the nearest one, and a ramp without
+/// any stops is black.
+#[derive(Clone, Debug)]
+pub struct ColorRamp {
+	stops: Vec<(f64, Color)>
+}
+
+impl ColorRamp {
+	pub fn new(mut stops: Vec<(f64, Color)>) -> Self {
+		stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+		ColorRamp { stops }
+	}
+
+	/// Ramp from `a` at 0 to `b` at 1.
+	pub fn between(a: Color, b: Color) -> Self {
+		Self::new(vec![(0., a), (1., b)])
+	}
+
+	pub fn color(&self, value: f64) -> Color {
+		let index = self.stops.iter().position(|stop| stop.0 > value);
+
+		match index {
+			Some(0) => self.stops[0].1,
+			Some(i) => {
+				let (start, end) = (self.stops[i - 1], self.stops[i]);
+				start.1.lerp(&end.1, (value - start.0) / (end.0 - start.0))
+			}
+			None => self.stops.last().map_or(Color::default(), |stop| stop.1)
+		}
+	}
+}
+
+/// Colors a surface by looking up a pattern at the point that was hit.
+///
+/// The pattern is evaluated in the space of `transform`, which is the world by
+/// default. Giving it the position of an object makes the pattern move and
+/// rotate with the object.
+#[derive(Clone, Debug)]
+pub struct Procedural<P: Pattern> {
+	pub pattern: P,
+	pub ramp: ColorRamp,
+	pub transform: Pos
+}
+
+impl<P: Pattern> Procedural<P> {
+	pub fn new(pattern: P, ramp: ColorRamp) -> Self {
+		Procedural { pattern, ramp, transform: Pos::identity() }
+	}
+}
+
+impl<P: Pattern> Albedo for Procedural<P> {
+	fn albedo(&self, result: HitResult) -> Color {
+		let point = self.transform.inversed() * result.pos();
+		self.ramp.color(self.pattern.value(point))
+	}
+}
@@ -0,0 +1,106 @@
+use std::io;
+use std::path::Path;
+use serde::Deserialize;
+use crate::math::hit::HitResult;
+use crate::math::color::Color;
+use crate::material::albedo::Albedo;
+use crate::image::{self, Image};
+
+/// What happens to texture coordinates outside of 0 to 1.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum Wrap {
+	#[default]
+	Repeat,
+	Mirror,
+	Clamp
+}
+
+impl Wrap {
+	fn apply(&self, index: isize, size: usize) -> usize {
+		let size = size as isize;
+
+		let index = match self {
+			Wrap::Repeat => index.rem_euclid(size),
+			Wrap::Mirror => {
+				let period = index.rem_euclid(size * 2);
+				if period < size { period } else { size * 2 - 1 - period }
+			}
+			Wrap::Clamp => index.max(0).min(size - 1)
+		};
+
+		index as usize
+	}
+}
+
+/// How the values in an image file are stored.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum Encoding {
+	/// Colors, which are almost always sRGB encoded.
+	#[default]
+	Srgb,
+	/// Data like roughness or masks, which are used as is.
+	Linear
+}
+
+impl Encoding {
+	fn decode(&self, color: Color) -> Color {
+		fn s2l(component: f64) -> f64 {
+			if component > 0.04045 {
+				((component + 0.055) / 1.055).powf(2.4)
+			} else {
+				component / 12.92
+			}
+		}
+
+		match self {
+			Encoding::Srgb => Color(s2l(color.0), s2l(color.1), s2l(color.2)),
+			Encoding::Linear => color
+		}
+	}
+}
+
+/// Image mapped onto a shape using its texture coordinates, with bilinear
+/// filtering. `v` goes from the bottom of the image to the top.
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+	/// Linear colors.
+	pub image: Image,
+	pub wrap: Wrap
+}
+
+impl ImageTexture {
+	pub fn new(image: Image, wrap: Wrap) -> Self {
+		ImageTexture { image, wrap }
+	}
+
+	/// Loads a PNG or PPM file.
+	pub fn open<P: AsRef<Path>>(path: P, encoding: Encoding, wrap: Wrap) -> io::Result<Self> {
+		let mut image = image::load_ldr(path)?;
+
+		for pixel in &mut image.pixels {
+			*pixel = encoding.decode(*pixel);
+		}
+
+		Ok(Self::new(image, wrap))
+	}
+
+	fn texel(&self, x: isize, y: isize) -> Color {
+		self.image.get(self.wrap.apply(x, self.image.width), self.wrap.apply(y, self.image.height))
+	}
+}
+
+impl Albedo for ImageTexture {
+	fn albedo(&self, result: HitResult) -> Color {
+		// texel centers are at half-integer coordinates
+		let x = result.uv.x * self.image.width as f64 - 0.5;
+		let y = (1. - result.uv.y) * self.image.height as f64 - 0.5;
+		let (x0, y0) = (x.floor(), y.floor());
+		let (fx, fy) = (x - x0, y - y0);
+		let (x0, y0) = (x0 as isize, y0 as isize);
+
+		let top = self.texel(x0, y0).lerp(&self.texel(x0 + 1, y0), fx);
+		let bottom = self.texel(x0, y0 + 1).lerp(&self.texel(x0 + 1, y0 + 1), fx);
+
+		top.lerp(&bottom, fy)
+	}
+}
@@ -1,8 +1,14 @@
-use crate::material::Material;
+use crate::material::{Material, Scatter};
 use crate::math::hit::HitResult;
 use crate::math::color::Color;
-use crate::hittable::Hittable;
+use crate::math::space::Vec3;
+use crate::material::albedo::Albedo;
+use crate::rng::gen_sampler_once;
 
+/// Combines two materials. `Add` and `Lerp` pick one of them at random for
+/// every bounce. `Mul`, `Sub` and `Div` bounce off `A` and tint the result
+/// with the albedo of `B`, with one minus it, or with one over it, where
+/// black channels stay black.
 #[derive(Copy, Clone, Debug)]
 pub enum Composite<A: Material, B: Material> {
 	Add(A, B),
@@ -12,23 +18,112 @@ pub enum Composite<A: Material, B: Material> {
 	Lerp(A, B, f64),
 }
 
-impl<A: Material, B: Material> Material for Composite<A, B> {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
+impl<A: Material, B: Material> Composite<A, B> {
+	// chance of sampling `A` and the factors that both materials are scaled by
+	fn weights(&self) -> (f64, f64, f64) {
+		match self {
+			Self::Add(..) => (0.5, 1., 1.),
+			Self::Mul(..) | Self::Sub(..) | Self::Div(..) => (1., 1., 0.),
+			Self::Lerp(_, _, t) => (1. - t, 1. - t, *t)
+		}
+	}
+
+	fn materials(&self) -> (&A, &B) {
 		match self {
-			Self::Add(a, b) | Self::Sub(a, b) |
-			Self::Div(a, b) | Self::Mul(a, b) |
-			Self::Lerp(a, b, _) => {
-				let color_a = a.color(scene, result.clone(), reflections);
-				let color_b = b.color(scene, result.clone(), reflections);
-
-				match self {
-					Self::Add(..) => color_a + color_b,
-					Self::Sub(..) => color_a - color_b,
-					Self::Div(..) => color_a / color_b,
-					Self::Mul(..) => color_a * color_b,
-					Self::Lerp(_, _, t) => color_a.lerp(&color_b, *t)
+			Self::Add(a, b) | Self::Sub(a, b) | Self::Div(a, b) |
+			Self::Mul(a, b) | Self::Lerp(a, b, _) => (a, b)
+		}
+	}
+
+	// what light off `A` is multiplied by, for the variants that only sample `A`
+	fn tint(&self, result: HitResult) -> Option<Color> {
+		let albedo = match self {
+			Self::Mul(_, b) | Self::Sub(_, b) | Self::Div(_, b) => b.albedo(result),
+			_ => return None
+		};
+
+		let channel = |f: fn(f64) -> f64| Color(f(albedo.0), f(albedo.1), f(albedo.2));
+
+		Some(match self {
+			Self::Sub(..) => channel(|c| (1. - c).max(0.)),
+			Self::Div(..) => channel(|c| if c > 0. { 1. / c } else { 0. }),
+			_ => albedo
+		})
+	}
+}
+
+impl<A: Material, B: Material> Albedo for Composite<A, B> {
+	fn albedo(&self, result: HitResult) -> Color {
+		let (a, b) = self.materials();
+
+		match self {
+			Self::Add(..) => a.albedo(result) + b.albedo(result),
+			Self::Lerp(_, _, t) => a.albedo(result).lerp(&b.albedo(result), *t),
+			_ => a.albedo(result) * self.tint(result).unwrap_or(Color::splat(1.))
+		}
+	}
+}
+
+impl<A: Material, B: Material> Material for Composite<A, B> {
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		let (a, b) = self.materials();
+		let (chance_a, factor_a, factor_b) = self.weights();
+
+		let scatter = if gen_sampler_once() < chance_a {
+			let scatter = a.sample(result)?;
+			let tint = self.tint(result).unwrap_or(Color::splat(1.));
+			Scatter { weight: scatter.weight * tint * (factor_a / chance_a), ..scatter }
+		} else {
+			let scatter = b.sample(result)?;
+			Scatter { weight: scatter.weight * (factor_b / (1. - chance_a)), ..scatter }
+		};
+
+		// directions that both materials could have picked are weighted by the
+		// combined density
+		match scatter.pdf {
+			Some(_) => {
+				let dir = scatter.ray.dir.normalized();
+				let pdf = self.pdf(result, dir);
+
+				if pdf > 0. {
+					Some(Scatter { weight: self.eval(result, dir) / pdf, pdf: Some(pdf), ..scatter })
+				} else {
+					None
 				}
 			}
+			None => Some(scatter)
+		}
+	}
+
+	fn eval(&self, result: HitResult, dir: Vec3) -> Color {
+		let (a, b) = self.materials();
+		let (_, factor_a, factor_b) = self.weights();
+
+		match self.tint(result) {
+			Some(tint) => a.eval(result, dir) * tint,
+			None => a.eval(result, dir) * factor_a + b.eval(result, dir) * factor_b
+		}
+	}
+
+	fn pdf(&self, result: HitResult, dir: Vec3) -> f64 {
+		let (a, b) = self.materials();
+		let (chance_a, _, _) = self.weights();
+
+		a.pdf(result, dir) * chance_a + b.pdf(result, dir) * (1. - chance_a)
+	}
+
+	fn emission(&self, result: HitResult) -> Color {
+		let (a, b) = self.materials();
+		let (_, factor_a, factor_b) = self.weights();
+
+		match self.tint(result) {
+			Some(tint) => a.emission(result) * tint,
+			None => a.emission(result) * factor_a + b.emission(result) * factor_b
 		}
 	}
-}
\ No newline at end of file
+
+	fn is_emissive(&self) -> bool {
+		let (a, b) = self.materials();
+		a.is_emissive() || b.is_emissive()
+	}
+}
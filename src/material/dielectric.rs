@@ -0,0 +1,105 @@
+use crate::material::{Material, Scatter};
+use crate::math::hit::HitResult;
+use crate::math::color::Color;
+use crate::math::ray::Ray;
+use crate::math::space::Vec3;
+use crate::material::albedo::Albedo;
+use crate::material::albedo::solid::Solid;
+use crate::math::spectrum::hero_wavelength;
+use crate::rng::gen_sampler_once;
+
+// Fraunhofer lines that dispersion is measured with, in nanometers
+const D_LINE: f64 = 587.6;
+const F_LINE: f64 = 486.1;
+const C_LINE: f64 = 656.3;
+
+/// Transparent material like glass or water. The second field is the index of
+/// refraction of the inside of the object, the outside is assumed to be air.
+/// The third is the Abbe number, which says how little the index changes with
+/// the wavelength. Lower numbers split light into more of a rainbow, and 0
+/// turns dispersion off. It only shows up when rendering spectrally.
+#[derive(Copy, Clone, Debug)]
+pub struct Dielectric<A: Albedo>(pub A, pub f64, pub f64);
+
+impl Dielectric<Solid> {
+	pub fn new(ior: f64) -> Self {
+		Self::colored(Color(1., 1., 1.), ior)
+	}
+
+	pub fn colored(color: Color, ior: f64) -> Self {
+		Dielectric(Solid(color), ior, 0.)
+	}
+}
+
+impl<A: Albedo> Dielectric<A> {
+	/// Glass with an Abbe number, which splits light into colors.
+	pub fn dispersive(albedo: A, ior: f64, abbe: f64) -> Self {
+		Dielectric(albedo, ior, abbe)
+	}
+
+	/// Index of refraction at `wavelength`, from Cauchy's equation fitted to
+	/// the index at the D line and the Abbe number.
+	pub fn ior_at(&self, wavelength: f64) -> f64 {
+		if self.2 <= 0. {
+			return self.1
+		}
+
+		let b = (self.1 - 1.) / (self.2 * (1. / (F_LINE * F_LINE) - 1. / (C_LINE * C_LINE)));
+		let a = self.1 - b / (D_LINE * D_LINE);
+
+		a + b / (wavelength * wavelength)
+	}
+
+	// exact fresnel equations for unpolarized light, eta is n_incident / n_transmitted
+	pub fn fresnel(cos_i: f64, cos_t: f64, eta: f64) -> f64 {
+		let rs = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+		let rp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+		(rs * rs + rp * rp) / 2.
+	}
+
+	/// Returns the direction of the refracted ray, or `None` on total internal
+	/// reflection, along with the fraction of light that is reflected.
+	pub fn refract(dir: Vec3, normal: Vec3, eta: f64) -> (Option<Vec3>, f64) {
+		let cos_i = (-dir.dot(normal)).min(1.);
+		let sin2_t = eta * eta * (1. - cos_i * cos_i);
+
+		if sin2_t >= 1. {
+			return (None, 1.)
+		}
+
+		let cos_t = (1. - sin2_t).sqrt();
+		let refracted = dir * eta + normal * (eta * cos_i - cos_t);
+
+		(Some(refracted), Self::fresnel(cos_i, cos_t, eta))
+	}
+}
+
+impl<A: Albedo> Albedo for Dielectric<A> {
+	fn albedo(&self, result: HitResult) -> Color {
+		self.0.albedo(result)
+	}
+}
+
+impl<A: Albedo> Material for Dielectric<A> {
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		let normal = result.normal_abs();
+		let wavelength = hero_wavelength().filter(|_| self.2 > 0.);
+		let ior = wavelength.map_or(self.1, |wavelength| self.ior_at(wavelength));
+		let eta = if result.is_back() { ior } else { 1. / ior };
+		let (refracted, reflectance) = Self::refract(result.ray.dir.normalized(), normal, eta);
+		let wavelength_dependent = wavelength.is_some();
+
+		match refracted {
+			Some(direction) if gen_sampler_once() >= reflectance => {
+				let remaining_length = result.ray.length() * (1. - result.t);
+				let origin: Vec3 = result.pos() - normal * 1e-10;
+
+				let refracted = Ray::new(origin, direction.normalized() * remaining_length).with_time(result.ray.time);
+
+				Some(Scatter { wavelength_dependent, ..Scatter::specular(refracted, self.albedo(result)) })
+			}
+			_ => Some(Scatter { wavelength_dependent, ..Scatter::specular(result.reflected(), Color::splat(1.)) })
+		}
+	}
+}
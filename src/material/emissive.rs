@@ -1,14 +1,20 @@
 use crate::material::albedo::Albedo;
 use crate::math::hit::HitResult;
 use crate::math::color::Color;
-use crate::material::Material;
-use crate::hittable::Hittable;
+use crate::math::space::Vec3;
+use crate::material::{Material, Scatter};
 use crate::material::lambertian::Lambertian;
 use crate::material::albedo::solid::Solid;
 
 #[derive(Debug)]
 pub struct Emissive<A: Albedo>(pub A, f64);
 
+impl<A: Albedo> Emissive<A> {
+	pub fn new(albedo: A, strength: f64) -> Self {
+		Self(albedo, strength)
+	}
+}
+
 impl Emissive<Solid> {
 	pub fn solid(color: Color) -> Self {
 		let max_component = color.0.max(color.1).max(color.2).max(1.);
@@ -22,8 +28,25 @@ impl<A: Albedo> Albedo for Emissive<A> {
 	}
 }
 
+// also reflects light like a white diffuse surface
 impl<A: Albedo> Material for Emissive<A> {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
-		Lambertian::<A>::color(scene, result, reflections) + self.albedo(result) * self.1
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		Some(Lambertian::<A>::scatter(result, Color::splat(1.)))
+	}
+
+	fn eval(&self, result: HitResult, dir: Vec3) -> Color {
+		Color::splat(Lambertian::<A>::pdf(result, dir))
+	}
+
+	fn pdf(&self, result: HitResult, dir: Vec3) -> f64 {
+		Lambertian::<A>::pdf(result, dir)
+	}
+
+	fn emission(&self, result: HitResult) -> Color {
+		self.albedo(result) * self.1
+	}
+
+	fn is_emissive(&self) -> bool {
+		self.1 > 0.
 	}
 }
@@ -1,9 +1,13 @@
-use crate::material::Material;
+use crate::material::{Material, Scatter};
 use crate::math::hit::HitResult;
 use crate::math::color::Color;
-use crate::hittable::Hittable;
+use crate::math::space::Vec3;
 use crate::material::albedo::Albedo;
 use crate::material::lambertian::Lambertian;
+use crate::rng::gen_sampler_once;
+
+// how often rays are scattered diffusely instead of being reflected
+const DIFFUSE_CHANCE: f64 = 0.75;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Glossy<A: Albedo>(pub A);
@@ -15,26 +19,20 @@ impl<A: Albedo> Albedo for Glossy<A> {
 }
 
 impl<A: Albedo> Material for Glossy<A> {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
-		if Lambertian::<A>::random_vec().y > -0.5 {
-			Lambertian::<A>::color(scene, result, reflections) * self.albedo(result)
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		if gen_sampler_once() < DIFFUSE_CHANCE {
+			let scatter = Lambertian::<A>::scatter(result, self.albedo(result));
+			Some(Scatter { pdf: scatter.pdf.map(|pdf| pdf * DIFFUSE_CHANCE), ..scatter })
 		} else {
-			HitResult::get_color(
-				scene.ray_trace(&result.reflected()),
-				scene,
-				reflections - 1
-			)
+			Some(Scatter::specular(result.reflected(), Color::splat(1.)))
 		}
+	}
 
-		//let mut color = Lambertian::<A>::color(scene, result, reflections) * self.albedo(result);
-
-		//let reflected_color = HitResult::get_color(
-		//	scene.ray_trace(&result.reflected()),
-		//	scene,
-		//	reflections - 1
-		//);
+	fn eval(&self, result: HitResult, dir: Vec3) -> Color {
+		self.albedo(result) * (Lambertian::<A>::pdf(result, dir) * DIFFUSE_CHANCE)
+	}
 
-		//color += reflected_color * reflected_color.luminance().min(1.) * (1. + result.ray.dir.normalized().dot(result.normal_abs()));
-		//color
+	fn pdf(&self, result: HitResult, dir: Vec3) -> f64 {
+		Lambertian::<A>::pdf(result, dir) * DIFFUSE_CHANCE
 	}
 }
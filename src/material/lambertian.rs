@@ -1,13 +1,12 @@
-use crate::material::Material;
+use crate::material::{Material, Scatter};
 use crate::math::ray::Ray;
 use crate::math::color::Color;
-use crate::hittable::Hittable;
 use crate::math::space::Vec3;
 use std::f64::consts::PI;
 use crate::math::hit::HitResult;
 use crate::material::albedo::solid::Solid;
 use crate::material::albedo::Albedo;
-use crate::rng::RngRangeWyrandContainer;
+use crate::rng::gen_sampler_range;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Lambertian<A: Albedo>(pub A);
@@ -18,32 +17,37 @@ impl Lambertian<Solid> {
 	}
 }
 
-thread_local!(
-	static THREAD_2PI: RngRangeWyrandContainer = RngRangeWyrandContainer::range(0., PI * 2.);
-	static THREAD_UNIT: RngRangeWyrandContainer = RngRangeWyrandContainer::range(-1., 1.);
-);
-
 impl<A: Albedo> Lambertian<A> {
 	pub fn random_vec() -> Vec3 {
-		let a = THREAD_2PI.with(RngRangeWyrandContainer::gen);
-		let z = THREAD_UNIT.with(RngRangeWyrandContainer::gen);
+		let a = gen_sampler_range(0., PI * 2.);
+		let z = gen_sampler_range(-1., 1.);
 		let r = (1f64 - z * z).sqrt();
 
 		Vec3::new(r * a.cos(), r * a.sin(), z)
 	}
 
-	pub fn color(scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
+	/// Cosine weighted bounce off the side of the surface that was hit.
+	pub fn scatter(result: HitResult, albedo: Color) -> Scatter {
 		let remaining_length = result.ray.length() * (1. - result.t);
 		let normal = result.normal_abs();
 
 		let origin: Vec3 = result.pos() + normal * 1e-10;
-		let direction = (normal + Lambertian::<A>::random_vec()).normalized() * remaining_length;
+		let direction = (normal + Lambertian::<A>::random_vec()).normalized();
+
+		Scatter {
+			ray: Ray::new(origin, direction * remaining_length).with_time(result.ray.time),
+			weight: albedo,
+			pdf: Some(Lambertian::<A>::pdf(result, direction)),
+			passes_through: false,
+			wavelength_dependent: false,
+			null: false
+		}
+	}
 
-		HitResult::get_color(
-			scene.ray_trace(&Ray::new(origin, direction)),
-			scene,
-			reflections - 1
-		)
+	/// Cosine term over pi, which is both the BSDF of a white surface times the
+	/// cosine and the density of `scatter`.
+	pub fn pdf(result: HitResult, dir: Vec3) -> f64 {
+		result.normal_abs().dot(dir).max(0.) / PI
 	}
 }
 
@@ -54,7 +58,15 @@ impl<A: Albedo> Albedo for Lambertian<A> {
 }
 
 impl<A: Albedo> Material for Lambertian<A> {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
-		Lambertian::<A>::color(scene, result, reflections) * self.albedo(result)
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		Some(Lambertian::<A>::scatter(result, self.albedo(result)))
+	}
+
+	fn eval(&self, result: HitResult, dir: Vec3) -> Color {
+		self.albedo(result) * Lambertian::<A>::pdf(result, dir)
+	}
+
+	fn pdf(&self, result: HitResult, dir: Vec3) -> f64 {
+		Lambertian::<A>::pdf(result, dir)
 	}
 }
@@ -1,7 +1,6 @@
-use crate::material::Material;
+use crate::material::{Material, Scatter};
 use crate::math::hit::HitResult;
 use crate::math::color::Color;
-use crate::hittable::Hittable;
 use crate::math::ray::Ray;
 use crate::material::albedo::Albedo;
 
@@ -15,9 +14,9 @@ impl Albedo for Mapper {
 }
 
 impl Material for Mapper {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
 		let normal = result.normal;
-		let emit = Ray::new(result.pos() + normal * 1e-10, normal * result.reflected().length());
-		HitResult::get_color(scene.ray_trace(&emit), scene, reflections - 1)
+		let emit = Ray::new(result.pos() + normal * 1e-10, normal * result.reflected().length()).with_time(result.ray.time);
+		Some(Scatter::specular(emit, Color::splat(1.)))
 	}
 }
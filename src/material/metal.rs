@@ -1,7 +1,6 @@
-use crate::material::Material;
+use crate::material::{Material, Scatter};
 use crate::math::hit::HitResult;
 use crate::math::color::Color;
-use crate::hittable::Hittable;
 use crate::material::lambertian::Lambertian;
 use crate::material::albedo::solid::Solid;
 use crate::material::albedo::Albedo;
@@ -26,16 +25,12 @@ impl<A: Albedo> Albedo for Metal<A> {
 }
 
 impl<A: Albedo> Material for Metal<A> {
-	fn color(&self, scene: &dyn Hittable, mut result: HitResult, reflections: u8) -> Color {
+	fn sample(&self, mut result: HitResult) -> Option<Scatter> {
 		let albedo = self.albedo(result);
 
 		result.normal += Lambertian::<A>::random_vec() * self.1;
 		result.normal.normalize();
 
-		HitResult::get_color(
-			scene.ray_trace(&result.reflected()),
-			scene,
-			reflections - 1
-		) * albedo
+		Some(Scatter::specular(result.reflected(), albedo))
 	}
 }
@@ -1,7 +1,8 @@
 use crate::material::albedo::Albedo;
-use crate::hittable::Hittable;
 use crate::math::hit::HitResult;
 use crate::math::color::Color;
+use crate::math::ray::Ray;
+use crate::math::space::Vec3;
 
 pub mod albedo;
 
@@ -11,7 +12,94 @@ pub mod metal;
 pub mod glossy;
 pub mod mapper;
 pub mod emissive;
+pub mod dielectric;
+pub mod composite;
+
+/// Where a ray goes after hitting a surface, picked by `Material::sample`.
+#[derive(Copy, Clone, Debug)]
+pub struct Scatter {
+	pub ray: Ray,
+	/// How much of the light coming back along `ray` makes it towards the
+	/// incoming ray. This is the BSDF times the cosine term, divided by `pdf`.
+	pub weight: Color,
+	/// Probability density of picking the direction of `ray`, with respect to
+	/// solid angle. `None` for directions that can't be picked any other way,
+	/// like perfect mirrors, which means that light sampling can't find them
+	/// either.
+	pub pdf: Option<f64>,
+	/// The ray just continues on, like through the back of a portal, so this
+	/// doesn't count as a bounce.
+	pub passes_through: bool,
+	/// The direction depends on `spectrum::hero_wavelength`, like in
+	/// dispersive glass, so only that wavelength can carry on along it.
+	pub wavelength_dependent: bool,
+	/// Nothing really happened, and the ray carries on in the same direction,
+	/// like at null collisions in a `Medium`. Lights found along it are weighted
+	/// as if the last real bounce had sampled them, since light sampling from
+	/// there sees through media. Implies `passes_through`.
+	pub null: bool
+}
+
+impl Scatter {
+	/// Scatter with a direction that can only be reached by sampling the
+	/// material.
+	pub fn specular(ray: Ray, weight: Color) -> Self {
+		Scatter { ray, weight, pdf: None, passes_through: false, wavelength_dependent: false, null: false }
+	}
+}
 
 pub trait Material: Albedo {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color;
+	/// Picks the direction the ray continues in, or `None` if it's absorbed.
+	fn sample(&self, result: HitResult) -> Option<Scatter>;
+
+	/// BSDF times the cosine term for light coming from the normalized
+	/// direction `dir`. Directions with no density (see `Scatter::pdf`) are
+	/// left out.
+	fn eval(&self, _result: HitResult, _dir: Vec3) -> Color {
+		Color::default()
+	}
+
+	/// Density with which `sample` picks the normalized direction `dir`.
+	fn pdf(&self, _result: HitResult, _dir: Vec3) -> f64 {
+		0.
+	}
+
+	/// Light given off by the surface itself.
+	fn emission(&self, _result: HitResult) -> Color {
+		Color::default()
+	}
+
+	/// Whether `emission` can ever return anything other than black. Shapes
+	/// with emissive materials are added to the scene's lights.
+	fn is_emissive(&self) -> bool {
+		false
+	}
+}
+
+impl Albedo for Box<dyn Material> {
+	fn albedo(&self, result: HitResult) -> Color {
+		self.as_ref().albedo(result)
+	}
+}
+
+impl Material for Box<dyn Material> {
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		self.as_ref().sample(result)
+	}
+
+	fn eval(&self, result: HitResult, dir: Vec3) -> Color {
+		self.as_ref().eval(result, dir)
+	}
+
+	fn pdf(&self, result: HitResult, dir: Vec3) -> f64 {
+		self.as_ref().pdf(result, dir)
+	}
+
+	fn emission(&self, result: HitResult) -> Color {
+		self.as_ref().emission(result)
+	}
+
+	fn is_emissive(&self) -> bool {
+		self.as_ref().is_emissive()
+	}
 }
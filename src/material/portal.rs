@@ -1,7 +1,6 @@
 use crate::math::space::Pos;
-use crate::hittable::Hittable;
 use crate::math::ray::Ray;
-use crate::material::Material;
+use crate::material::{Material, Scatter};
 use crate::math::color::Color;
 use crate::math::hit::HitResult;
 use crate::material::albedo::Albedo;
@@ -16,14 +15,14 @@ impl Albedo for Portal {
 }
 
 impl Material for Portal {
-	fn color(&self, scene: &dyn Hittable, result: HitResult, reflections: u8) -> Color {
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
 		let cut = result.ray.from(result.t + 1e-10);
 
 		if result.is_back() {
-			HitResult::get_color(scene.ray_trace(&cut), scene, reflections)
+			Some(Scatter { passes_through: true, ..Scatter::specular(cut, Color::splat(1.)) })
 		} else {
-			let new = Ray::new(self.0 * cut.pos, cut.dir.rotated_by(self.0.rotation));
-			HitResult::get_color(scene.ray_trace(&new), scene, reflections - 1)
+			let new = Ray::new(self.0 * cut.pos, cut.dir.rotated_by(self.0.rotation)).with_time(cut.time);
+			Some(Scatter::specular(new, Color::splat(1.)))
 		}
 	}
 }
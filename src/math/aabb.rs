@@ -0,0 +1,102 @@
+use crate::math::space::Vec3;
+use crate::math::ray::Ray;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+	pub min: Vec3,
+	pub max: Vec3
+}
+
+impl Aabb {
+	pub fn new(a: Vec3, b: Vec3) -> Self {
+		Aabb { min: a.min_by_component(b), max: a.max_by_component(b) }
+	}
+
+	pub fn around(center: Vec3, half_extent: Vec3) -> Self {
+		Aabb::new(center - half_extent, center + half_extent)
+	}
+
+	pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Option<Self> {
+		points.into_iter().fold(None, |aabb: Option<Aabb>, point| Some(match aabb {
+			Some(aabb) => aabb.grow(point),
+			None => Aabb::new(point, point)
+		}))
+	}
+
+	pub fn union(&self, other: &Aabb) -> Aabb {
+		Aabb { min: self.min.min_by_component(other.min), max: self.max.max_by_component(other.max) }
+	}
+
+	pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+		let min = self.min.max_by_component(other.min);
+		let max = self.max.min_by_component(other.max);
+
+		if min.x > max.x || min.y > max.y || min.z > max.z {
+			None
+		} else {
+			Some(Aabb { min, max })
+		}
+	}
+
+	pub fn grow(&self, point: Vec3) -> Aabb {
+		Aabb { min: self.min.min_by_component(point), max: self.max.max_by_component(point) }
+	}
+
+	/// Grows the box by `amount` in every direction, used to give flat shapes
+	/// some thickness so the slab test never has to divide zero by zero.
+	pub fn padded(&self, amount: f64) -> Aabb {
+		Aabb { min: self.min - Vec3::broadcast(amount), max: self.max + Vec3::broadcast(amount) }
+	}
+
+	pub fn size(&self) -> Vec3 {
+		self.max - self.min
+	}
+
+	pub fn centroid(&self) -> Vec3 {
+		(self.min + self.max) / 2.
+	}
+
+	pub fn surface_area(&self) -> f64 {
+		let size = self.size();
+		2. * (size.x * size.y + size.y * size.z + size.z * size.x)
+	}
+
+	pub fn contains(&self, point: Vec3) -> bool {
+		point.x >= self.min.x && point.x <= self.max.x &&
+			point.y >= self.min.y && point.y <= self.max.y &&
+			point.z >= self.min.z && point.z <= self.max.z
+	}
+
+	/// Slab test. Returns the range of `t` (clamped to the extent of the ray)
+	/// for which the ray is inside of the box.
+	pub fn intersect(&self, ray: &Ray) -> Option<(f64, f64)> {
+		let mut t_min = 0f64;
+		let mut t_max = 1f64;
+
+		let axes = [
+			(self.min.x, self.max.x, ray.pos.x, ray.dir.x),
+			(self.min.y, self.max.y, ray.pos.y, ray.dir.y),
+			(self.min.z, self.max.z, ray.pos.z, ray.dir.z)
+		];
+
+		for &(min, max, pos, dir) in &axes {
+			let inv = 1. / dir;
+			let mut t0 = (min - pos) * inv;
+			let mut t1 = (max - pos) * inv;
+
+			if inv < 0. {
+				std::mem::swap(&mut t0, &mut t1);
+			}
+
+			// written this way around so that NaNs are ignored
+			t_min = if t0 > t_min { t0 } else { t_min };
+			t_max = if t1 < t_max { t1 } else { t_max };
+
+			if t_max < t_min {
+				return None
+			}
+		}
+
+		Some((t_min, t_max))
+	}
+}
@@ -1,5 +1,6 @@
 use std::ops::{Add, Sub, AddAssign, SubAssign, Div, DivAssign, Mul, MulAssign};
 use crate::math::space::Vec3;
+use crate::math::color_space::ColorMatrix;
 
 #[repr(C)]
 #[derive(Default, Debug, Copy, Clone, PartialEq)]
@@ -128,6 +129,117 @@ impl Color {
 		)
 	}
 
+	/// Stephen Hill's fit of the ACES reference rendering and sRGB output
+	/// transforms, including the conversion to and from the ACES color space.
+	pub fn tone_map_aces_fitted(&self, white_point: f64) -> Color {
+		const INPUT: ColorMatrix = ColorMatrix([
+			[0.59719, 0.35458, 0.04823],
+			[0.07600, 0.90834, 0.01566],
+			[0.02840, 0.13383, 0.83777]
+		]);
+
+		const OUTPUT: ColorMatrix = ColorMatrix([
+			[1.60475, -0.53108, -0.07367],
+			[-0.10208, 1.10813, -0.00605],
+			[-0.00327, -0.07276, 1.07602]
+		]);
+
+		let fit = |v: f64| (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081);
+
+		let white_point = fit(white_point);
+		let color = INPUT.transform(*self);
+
+		OUTPUT.transform(Color(fit(color.0), fit(color.1), fit(color.2)) / white_point)
+	}
+
+	/// Troy Sobotka's AgX, as approximated by Benjamin Wrensch, which
+	/// desaturates very bright colors towards white instead of skewing their
+	/// hue. The white point is the top of the range of the log encoding.
+	pub fn tone_map_agx(&self, white_point: f64) -> Color {
+		const INSET: ColorMatrix = ColorMatrix([
+			[0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+			[0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+			[0.0423756549057051, 0.0784336, 0.879142973793104]
+		]);
+
+		const OUTSET: ColorMatrix = ColorMatrix([
+			[1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+			[-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+			[-0.0529716355144438, -0.0980434501171241, 1.15107367264116]
+		]);
+
+		const MIN_EV: f64 = -12.47393;
+		let max_ev = white_point.log2().max(MIN_EV + 1.);
+
+		let contrast = |v: f64| {
+			let x = (v.max(1e-10).log2().max(MIN_EV).min(max_ev) - MIN_EV) / (max_ev - MIN_EV);
+			let x2 = x * x;
+			let x4 = x2 * x2;
+
+			15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+		};
+
+		let color = INSET.transform(*self);
+		let color = OUTSET.transform(Color(contrast(color.0), contrast(color.1), contrast(color.2)));
+
+		// the curve's output is meant for a display, so take the display's gamma
+		// back out for `to_srgb`
+		Color(color.0.max(0.), color.1.max(0.), color.2.max(0.)).powf(2.2)
+	}
+
+	/// Reinhard's operator with a white point, applied to the luminance so
+	/// that hues stay the same.
+	pub fn tone_map_reinhard_extended(&self, white_point: f64) -> Color {
+		let luminance = self.luminance();
+
+		if luminance <= 0. {
+			return Color::default()
+		}
+
+		let mapped = luminance * (1. + luminance / (white_point * white_point)) / (1. + luminance);
+		*self * (mapped / luminance)
+	}
+
+	/// John Hable's filmic curve from Uncharted 2, with its exposure bias of 2.
+	// http://filmicworlds.com/blog/filmic-tonemapping-operators/
+	pub fn tone_map_hable(&self, white_point: f64) -> Color {
+		let curve = |x: f64| {
+			const A: f64 = 0.15;
+			const B: f64 = 0.50;
+			const C: f64 = 0.10;
+			const D: f64 = 0.20;
+			const E: f64 = 0.02;
+			const F: f64 = 0.30;
+
+			(x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+		};
+
+		let white_point = curve(white_point);
+
+		Color(
+			curve(self.0 * 2.) / white_point,
+			curve(self.1 * 2.) / white_point,
+			curve(self.2 * 2.) / white_point
+		)
+	}
+
+	/// No curve at all: everything at or above the white point is clipped.
+	pub fn tone_map_neutral(&self, white_point: f64) -> Color {
+		let color = *self / white_point;
+		Color(color.0.max(0.).min(1.), color.1.max(0.).min(1.), color.2.max(0.).min(1.))
+	}
+
+	pub fn tone_map(&self, tone_mapper: ToneMapper, white_point: f64) -> Color {
+		match tone_mapper {
+			ToneMapper::Hejl2015 => self.tone_map_filmic_hejl2015(white_point),
+			ToneMapper::AcesFitted => self.tone_map_aces_fitted(white_point),
+			ToneMapper::AgX => self.tone_map_agx(white_point),
+			ToneMapper::Reinhard => self.tone_map_reinhard_extended(white_point),
+			ToneMapper::Hable => self.tone_map_hable(white_point),
+			ToneMapper::Neutral => self.tone_map_neutral(white_point)
+		}
+	}
+
 	#[inline]
 	pub fn to_srgb(&self) -> u32 {
 		fn l2s(component: f64) -> f64 {
@@ -191,3 +303,57 @@ impl Color {
 		self.0 * 0.2126 + self.1 * 0.7152 + self.2 * 0.0722
 	}
 }
+
+/// Curve that squeezes HDR colors into what a display can show. The white
+/// point is the brightness that ends up as white.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapper {
+	#[default]
+	Hejl2015,
+	AcesFitted,
+	AgX,
+	Reinhard,
+	Hable,
+	Neutral
+}
+
+impl ToneMapper {
+	pub const ALL: [ToneMapper; 6] = [
+		ToneMapper::Hejl2015,
+		ToneMapper::AcesFitted,
+		ToneMapper::AgX,
+		ToneMapper::Reinhard,
+		ToneMapper::Hable,
+		ToneMapper::Neutral
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			ToneMapper::Hejl2015 => "hejl",
+			ToneMapper::AcesFitted => "aces",
+			ToneMapper::AgX => "agx",
+			ToneMapper::Reinhard => "reinhard",
+			ToneMapper::Hable => "hable",
+			ToneMapper::Neutral => "neutral"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<ToneMapper> {
+		ToneMapper::ALL.iter().copied().find(|tone_mapper| tone_mapper.name() == name)
+	}
+
+	/// White point that gives the usual look of the curve.
+	pub fn default_white_point(self) -> f64 {
+		match self {
+			// what the viewer has always used
+			ToneMapper::Hejl2015 => 1.,
+			// about where the fit reaches 1 by itself
+			ToneMapper::AcesFitted => 25.,
+			// the top of the usual log encoding, at 4 stops above 1
+			ToneMapper::AgX => 4.026069f64.exp2(),
+			ToneMapper::Reinhard => 4.,
+			ToneMapper::Hable => 11.2,
+			ToneMapper::Neutral => 1.
+		}
+	}
+}
@@ -0,0 +1,187 @@
+use std::ops::Mul;
+use serde::Deserialize;
+use crate::math::color::Color;
+
+/// 3x3 matrix that turns one kind of RGB (or XYZ) color into another, stored
+/// row by row.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorMatrix(pub [[f64; 3]; 3]);
+
+impl ColorMatrix {
+	pub const IDENTITY: ColorMatrix = ColorMatrix([
+		[1., 0., 0.],
+		[0., 1., 0.],
+		[0., 0., 1.]
+	]);
+
+	fn diagonal(color: Color) -> Self {
+		ColorMatrix([
+			[color.0, 0., 0.],
+			[0., color.1, 0.],
+			[0., 0., color.2]
+		])
+	}
+
+	#[inline]
+	pub fn transform(&self, color: Color) -> Color {
+		let row = |r: &[f64; 3]| r[0] * color.0 + r[1] * color.1 + r[2] * color.2;
+		Color(row(&self.0[0]), row(&self.0[1]), row(&self.0[2]))
+	}
+
+	pub fn inverse(&self) -> Self {
+		let m = &self.0;
+
+		// transposed cofactors divided by the determinant
+		let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+
+		let adjugate = [
+			[cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+			[-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+			[cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)]
+		];
+
+		let determinant = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+
+		ColorMatrix([
+			[adjugate[0][0] / determinant, adjugate[0][1] / determinant, adjugate[0][2] / determinant],
+			[adjugate[1][0] / determinant, adjugate[1][1] / determinant, adjugate[1][2] / determinant],
+			[adjugate[2][0] / determinant, adjugate[2][1] / determinant, adjugate[2][2] / determinant]
+		])
+	}
+}
+
+impl Mul for ColorMatrix {
+	type Output = ColorMatrix;
+
+	fn mul(self, rhs: ColorMatrix) -> Self::Output {
+		let mut result = [[0.; 3]; 3];
+
+		for (row, result) in result.iter_mut().enumerate() {
+			for (column, value) in result.iter_mut().enumerate() {
+				*value = (0..3).map(|i| self.0[row][i] * rhs.0[i][column]).sum();
+			}
+		}
+
+		ColorMatrix(result)
+	}
+}
+
+// CIE 1931 xy chromaticities
+const D65: (f64, f64) = (0.3127, 0.3290);
+const D60: (f64, f64) = (0.32168, 0.33767);
+
+// von Kries style adaptation in the Bradford cone space, which is what most
+// color management systems use
+const BRADFORD: ColorMatrix = ColorMatrix([
+	[0.8951, 0.2664, -0.1614],
+	[-0.7502, 1.7135, 0.0367],
+	[0.0389, -0.0685, 1.0296]
+]);
+
+fn xy_to_xyz((x, y): (f64, f64)) -> Color {
+	Color(x / y, 1., (1. - x - y) / y)
+}
+
+/// Chromatic adaptation of XYZ colors seen under the white `from` to how they
+/// look under the white `to`, so that `from` becomes `to`.
+pub fn adaptation(from: Color, to: Color) -> ColorMatrix {
+	let source = BRADFORD.transform(from);
+	let destination = BRADFORD.transform(to);
+
+	BRADFORD.inverse() * ColorMatrix::diagonal(destination / source) * BRADFORD
+}
+
+/// Linear RGB color space, given by its primaries and white point. `Color`
+/// doesn't know which one it's in, so whoever makes one has to keep track.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum ColorSpace {
+	/// sRGB primaries, which is what images and displays usually are.
+	#[default]
+	Rec709,
+	/// Ultra HD television.
+	Rec2020,
+	/// The ACES working space, with AP1 primaries and a white point near D60.
+	AcesCg,
+	/// DCI-P3 primaries with a D65 white point, like Apple's displays.
+	DisplayP3
+}
+
+impl ColorSpace {
+	pub const ALL: [ColorSpace; 4] = [ColorSpace::Rec709, ColorSpace::Rec2020, ColorSpace::AcesCg, ColorSpace::DisplayP3];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			ColorSpace::Rec709 => "rec709",
+			ColorSpace::Rec2020 => "rec2020",
+			ColorSpace::AcesCg => "acescg",
+			ColorSpace::DisplayP3 => "p3"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<ColorSpace> {
+		ColorSpace::ALL.iter().copied().find(|space| space.name() == name)
+	}
+
+	/// xy chromaticities of the red, green and blue primaries.
+	pub fn primaries(self) -> [(f64, f64); 3] {
+		match self {
+			ColorSpace::Rec709 => [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+			ColorSpace::Rec2020 => [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+			ColorSpace::AcesCg => [(0.713, 0.293), (0.165, 0.830), (0.128, 0.044)],
+			ColorSpace::DisplayP3 => [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)]
+		}
+	}
+
+	/// xy chromaticity of the white point.
+	pub fn white(self) -> (f64, f64) {
+		match self {
+			ColorSpace::AcesCg => D60,
+			_ => D65
+		}
+	}
+
+	/// XYZ of the white point, with a Y of 1.
+	pub fn white_xyz(self) -> Color {
+		xy_to_xyz(self.white())
+	}
+
+	/// Matrix from this space to CIE XYZ, scaled so that white has a Y of 1.
+	pub fn to_xyz(self) -> ColorMatrix {
+		let [r, g, b] = self.primaries().map(xy_to_xyz);
+
+		let primaries = ColorMatrix([
+			[r.0, g.0, b.0],
+			[r.1, g.1, b.1],
+			[r.2, g.2, b.2]
+		]);
+
+		// how much of every primary it takes to make white
+		let scale = primaries.inverse().transform(self.white_xyz());
+
+		primaries * ColorMatrix::diagonal(scale)
+	}
+
+	pub fn from_xyz(self) -> ColorMatrix {
+		self.to_xyz().inverse()
+	}
+
+	/// Matrix that turns colors in this space into ones that look the same in
+	/// `to`. If the white points differ, white stays white.
+	pub fn conversion(self, to: ColorSpace) -> ColorMatrix {
+		if self == to {
+			return ColorMatrix::IDENTITY
+		}
+
+		let adaptation = if self.white() == to.white() {
+			ColorMatrix::IDENTITY
+		} else {
+			adaptation(self.white_xyz(), to.white_xyz())
+		};
+
+		to.from_xyz() * adaptation * self.to_xyz()
+	}
+
+	pub fn convert(self, color: Color, to: ColorSpace) -> Color {
+		self.conversion(to).transform(color)
+	}
+}
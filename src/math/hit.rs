@@ -1,29 +1,23 @@
 use crate::math::ray::Ray;
-use crate::math::space::Vec3;
+use crate::math::space::{Vec2, Vec3};
 use crate::material::Material;
-use crate::hittable::Hittable;
-use crate::math::color::Color;
 
 #[derive(Copy, Clone, Debug)]
 pub struct HitResult<'a> {
 	pub ray: Ray,
 	pub t: f64,
 	pub normal: Vec3,
-	pub material: &'a dyn Material
+	/// Texture coordinates of the point that was hit, usually between 0 and 1.
+	pub uv: Vec2,
+	pub material: &'a dyn Material,
+	/// Index of the object in the scene that was hit, in the order they were
+	/// added. Shapes leave it at `None` for `Bvh` to fill in, so it's only
+	/// known after `TestScene::build_bvh`. Fog isn't an object, so it stays
+	/// `None` there.
+	pub object: Option<usize>
 }
 
 impl<'a> HitResult<'a> {
-	pub fn get_color(result: Option<HitResult>, scene: &dyn Hittable, reflections: u8) -> Color {
-		if reflections == 0 {
-			Color::default()
-		} else {
-			match result {
-				Some(result) => result.material.color(scene, result, reflections),
-				None => Color::default()
-			}
-		}
-	}
-
 	pub fn is_back(&self) -> bool {
 		self.ray.dir.dot(self.normal) > 0.
 	}
@@ -1,4 +1,8 @@
 pub mod color;
+pub mod color_space;
+pub mod spectrum;
 pub mod space;
 pub mod ray;
 pub mod hit;
+pub mod aabb;
+pub mod noise;
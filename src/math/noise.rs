@@ -0,0 +1,127 @@
+use crate::math::space::Vec3;
+
+/// Improved Perlin gradient noise, from Ken Perlin's 2002 paper. The same seed
+/// always gives the same noise.
+#[derive(Clone)]
+pub struct Perlin {
+	perm: [u8; 512]
+}
+
+impl std::fmt::Debug for Perlin {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Perlin")
+	}
+}
+
+impl Default for Perlin {
+	fn default() -> Self {
+		Perlin::new(0)
+	}
+}
+
+fn fade(t: f64) -> f64 {
+	t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+	a + t * (b - a)
+}
+
+// dot product with one of 12 gradients along the edges of a cube
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+	let h = hash & 15;
+	let u = if h < 8 { x } else { y };
+	let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+
+	(if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+impl Perlin {
+	pub fn new(seed: u64) -> Self {
+		let mut table = [0u8; 256];
+
+		for (i, value) in table.iter_mut().enumerate() {
+			*value = i as u8;
+		}
+
+		// Fisher-Yates shuffle driven by splitmix64
+		let mut state = seed;
+
+		for i in (1..256).rev() {
+			state = state.wrapping_add(0x9E3779B97F4A7C15);
+			let mut z = state;
+			z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+			z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+			z ^= z >> 31;
+
+			table.swap(i, (z % (i as u64 + 1)) as usize);
+		}
+
+		let mut perm = [0u8; 512];
+
+		for i in 0..512 {
+			perm[i] = table[i & 255];
+		}
+
+		Perlin { perm }
+	}
+
+	/// Noise between roughly -1 and 1, which is 0 at every integer point.
+	pub fn noise(&self, point: Vec3) -> f64 {
+		let (fx, fy, fz) = (point.x.floor(), point.y.floor(), point.z.floor());
+		let (x, y, z) = (point.x - fx, point.y - fy, point.z - fz);
+
+		let xi = (fx as i64 & 255) as usize;
+		let yi = (fy as i64 & 255) as usize;
+		let zi = (fz as i64 & 255) as usize;
+
+		let (u, v, w) = (fade(x), fade(y), fade(z));
+		let p = &self.perm;
+
+		let a = p[xi] as usize + yi;
+		let aa = p[a] as usize + zi;
+		let ab = p[a + 1] as usize + zi;
+		let b = p[xi + 1] as usize + yi;
+		let ba = p[b] as usize + zi;
+		let bb = p[b + 1] as usize + zi;
+
+		lerp(w,
+			lerp(v,
+				lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1., y, z)),
+				lerp(u, grad(p[ab], x, y - 1., z), grad(p[bb], x - 1., y - 1., z))
+			),
+			lerp(v,
+				lerp(u, grad(p[aa + 1], x, y, z - 1.), grad(p[ba + 1], x - 1., y, z - 1.)),
+				lerp(u, grad(p[ab + 1], x, y - 1., z - 1.), grad(p[bb + 1], x - 1., y - 1., z - 1.))
+			)
+		)
+	}
+
+	/// Fractal Brownian motion: octaves of noise, each with twice the frequency
+	/// and half the amplitude of the last. Between roughly -1 and 1.
+	pub fn fbm(&self, point: Vec3, octaves: u32) -> f64 {
+		self.octaves(point, octaves, |n| n)
+	}
+
+	/// Like `fbm`, but adds up the absolute value of every octave, which gives
+	/// sharp creases. Between 0 and roughly 1.
+	pub fn turbulence(&self, point: Vec3, octaves: u32) -> f64 {
+		self.octaves(point, octaves, f64::abs)
+	}
+
+	fn octaves<F: Fn(f64) -> f64>(&self, point: Vec3, octaves: u32, f: F) -> f64 {
+		let mut sum = 0.;
+		let mut total = 0.;
+		let mut amplitude = 1.;
+		let mut point = point;
+
+		for _ in 0..octaves.max(1) {
+			sum += f(self.noise(point)) * amplitude;
+			total += amplitude;
+			amplitude *= 0.5;
+			point *= 2.;
+		}
+
+		sum / total
+	}
+}
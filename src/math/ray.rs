@@ -6,12 +6,19 @@ use crate::material::albedo::solid::Solid;
 #[derive(Copy, Clone, PartialEq)]
 pub struct Ray {
 	pub pos: Vec3,
-	pub dir: Vec3
+	pub dir: Vec3,
+	/// Point in time during the exposure that the ray belongs to, between 0
+	/// and 1. Moving objects are intersected at this time.
+	pub time: f64
 }
 
 impl Ray {
 	pub fn new(origin: Vec3, direction: Vec3) -> Self {
-		Self { pos: origin, dir: direction }
+		Self { pos: origin, dir: direction, time: 0. }
+	}
+
+	pub fn with_time(self, time: f64) -> Self {
+		Self { time, ..self }
 	}
 }
 
@@ -48,7 +55,7 @@ impl Ray {
 
 	#[inline]
 	pub fn from(&self, t: f64) -> Ray {
-		Ray::new(self.at(t), self.dir * (1. - t))
+		Ray::new(self.at(t), self.dir * (1. - t)).with_time(self.time)
 	}
 
 	pub fn t_for(&self, point: Vec3) -> f64 {
@@ -67,7 +74,8 @@ impl Ray {
 	pub fn wobbled(&self, amount: f64) -> Ray {
 		Ray {
 			pos: self.pos,
-			dir: (self.dir + (Lambertian::<Solid>::random_vec() * amount)).normalized()
+			dir: (self.dir + (Lambertian::<Solid>::random_vec() * amount)).normalized(),
+			time: self.time
 		}
 	}
 }
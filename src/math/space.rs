@@ -1,4 +1,5 @@
-use ultraviolet::{DVec3, DIsometry3};
+use ultraviolet::{DVec2, DVec3, DIsometry3};
 
+pub type Vec2 = DVec2;
 pub type Vec3 = DVec3;
-pub type Pos = DIsometry3;
\ No newline at end of file
+pub type Pos = DIsometry3;
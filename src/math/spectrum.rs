@@ -0,0 +1,505 @@
+use std::cell::Cell;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Add, AddAssign, Mul, MulAssign, Div, DivAssign};
+use crate::math::color::Color;
+use crate::math::color_space::{ColorSpace, ColorMatrix, adaptation};
+
+#[cfg(not(debug_assertions))]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+thread_local!(
+	static HERO_WAVELENGTH: Cell<Option<f64>> = Cell::new(None);
+);
+
+/// Number of wavelengths every path carries.
+pub const WAVELENGTHS: usize = 4;
+
+/// Range of wavelengths that are sampled, in nanometers.
+pub const MIN_WAVELENGTH: f64 = 380.;
+pub const MAX_WAVELENGTH: f64 = 780.;
+
+/// Wavelength of the path being traced on this thread, or `None` when it's
+/// RGB. Materials whose direction depends on it, like dispersive glass, have
+/// to set `Scatter::wavelength_dependent`.
+pub fn hero_wavelength() -> Option<f64> {
+	HERO_WAVELENGTH.with(|wavelength| wavelength.get())
+}
+
+pub(crate) fn set_hero_wavelength(wavelength: Option<f64>) {
+	HERO_WAVELENGTH.with(|cell| cell.set(wavelength));
+}
+
+/// The wavelengths of a path, in nanometers. The first one is the hero
+/// wavelength, and the others are spread evenly across the range from there,
+/// so that every one of them is uniformly distributed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Wavelengths(pub [f64; WAVELENGTHS]);
+
+impl Wavelengths {
+	/// Probability density of every wavelength.
+	pub const PDF: f64 = 1. / (MAX_WAVELENGTH - MIN_WAVELENGTH);
+
+	/// `u` is a random number in [0, 1).
+	pub fn sample(u: f64) -> Self {
+		let mut wavelengths = [0.; WAVELENGTHS];
+
+		for (i, wavelength) in wavelengths.iter_mut().enumerate() {
+			let t = (u + i as f64 / WAVELENGTHS as f64).fract();
+			*wavelength = MIN_WAVELENGTH + t * (MAX_WAVELENGTH - MIN_WAVELENGTH);
+		}
+
+		Wavelengths(wavelengths)
+	}
+
+	pub fn hero(&self) -> f64 {
+		self.0[0]
+	}
+}
+
+/// Values of a spectrum at the wavelengths of a path.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SampledSpectrum(pub [f64; WAVELENGTHS]);
+
+impl SampledSpectrum {
+	pub fn splat(n: f64) -> Self {
+		SampledSpectrum([n; WAVELENGTHS])
+	}
+
+	pub fn max_value(&self) -> f64 {
+		self.0.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+	}
+
+	fn map(self, f: impl Fn(usize, f64) -> f64) -> Self {
+		let mut values = self.0;
+
+		for (i, value) in values.iter_mut().enumerate() {
+			*value = f(i, *value);
+		}
+
+		SampledSpectrum(values)
+	}
+}
+
+impl Add for SampledSpectrum {
+	type Output = SampledSpectrum;
+
+	fn add(self, rhs: SampledSpectrum) -> Self::Output {
+		self.map(|i, value| value + rhs.0[i])
+	}
+}
+
+impl AddAssign for SampledSpectrum {
+	fn add_assign(&mut self, rhs: SampledSpectrum) {
+		*self = *self + rhs;
+	}
+}
+
+impl Mul for SampledSpectrum {
+	type Output = SampledSpectrum;
+
+	fn mul(self, rhs: SampledSpectrum) -> Self::Output {
+		self.map(|i, value| value * rhs.0[i])
+	}
+}
+
+impl MulAssign for SampledSpectrum {
+	fn mul_assign(&mut self, rhs: SampledSpectrum) {
+		*self = *self * rhs;
+	}
+}
+
+impl Mul<f64> for SampledSpectrum {
+	type Output = SampledSpectrum;
+
+	fn mul(self, rhs: f64) -> Self::Output {
+		self.map(|_, value| value * rhs)
+	}
+}
+
+impl Div<f64> for SampledSpectrum {
+	type Output = SampledSpectrum;
+
+	fn div(self, rhs: f64) -> Self::Output {
+		self.map(|_, value| value / rhs)
+	}
+}
+
+impl DivAssign<f64> for SampledSpectrum {
+	fn div_assign(&mut self, rhs: f64) {
+		*self = *self / rhs;
+	}
+}
+
+// piecewise gaussian with a different width on either side of the mean
+fn lobe(wavelength: f64, mean: f64, left: f64, right: f64) -> f64 {
+	let t = (wavelength - mean) / if wavelength < mean { left } else { right };
+	(-0.5 * t * t).exp()
+}
+
+/// CIE 1931 color matching functions as XYZ, using the multi-lobe fit from
+/// Wyman, Sloan and Shirley's "Simple Analytic Approximations to the CIE XYZ
+/// Color Matching Functions".
+pub fn cie_xyz(wavelength: f64) -> Color {
+	Color(
+		1.056 * lobe(wavelength, 599.8, 37.9, 31.0) + 0.362 * lobe(wavelength, 442.0, 16.0, 26.7) - 0.065 * lobe(wavelength, 501.1, 20.4, 26.2),
+		0.821 * lobe(wavelength, 568.8, 46.9, 40.5) + 0.286 * lobe(wavelength, 530.9, 16.3, 31.1),
+		1.217 * lobe(wavelength, 437.0, 11.8, 36.0) + 0.681 * lobe(wavelength, 459.0, 26.0, 13.8)
+	)
+}
+
+// CIE standard illuminant D65 from 380 to 780 nm in steps of 10 nm
+const D65: [f64; 41] = [
+	49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861,
+	115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405, 104.046, 100., 96.3342,
+	95.788, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778,
+	78.2842, 69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054,
+	63.3828
+];
+
+/// Relative power of daylight at `wavelength`, 100 at 560 nm.
+pub fn d65(wavelength: f64) -> f64 {
+	let x = ((wavelength - 380.) / 10.).max(0.).min((D65.len() - 1) as f64);
+	let i = (x as usize).min(D65.len() - 2);
+
+	D65[i] + (D65[i + 1] - D65[i]) * (x - i as f64)
+}
+
+// Jakob and Hanika's "A Low-Dimensional Function Space for Efficient Spectral
+// Upsampling": a smooth spectrum for any reflectance is a sigmoid of a
+// quadratic, which only has three coefficients
+fn sigmoid(x: f64) -> f64 {
+	if x.is_infinite() {
+		return if x > 0. { 1. } else { 0. }
+	}
+
+	0.5 + x / (2. * (1. + x * x).sqrt())
+}
+
+// the coefficients work on wavelengths scaled to 0 to 1 across the range
+fn polynomial(coefficients: &[f64; 3], wavelength: f64) -> f64 {
+	let t = (wavelength - MIN_WAVELENGTH) / (MAX_WAVELENGTH - MIN_WAVELENGTH);
+	(coefficients[0] * t + coefficients[1]) * t + coefficients[2]
+}
+
+/// Resolution of the coefficient table along each axis.
+const RESOLUTION: usize = 32;
+
+/// Step between the wavelengths that fitting integrates over.
+const FIT_STEP: f64 = 5.;
+
+/// Step between the wavelengths that normalization integrates over.
+const INTEGRATION_STEP: f64 = 1.;
+
+/// Turns RGB colors in a working space into spectra and back, for spectral
+/// rendering. Reflectances become smooth spectra that have the same color
+/// under daylight, and lights become those spectra times daylight. Making one
+/// fits a table of sigmoid coefficients, which takes a moment.
+pub struct Spectral {
+	pub working_space: ColorSpace,
+	/// From XYZ under daylight to the working space, including adapting
+	/// daylight to its white point.
+	from_xyz: ColorMatrix,
+	/// Integral of the Y matching function.
+	y_integral: f64,
+	/// Scales D65 to a luminance of 1.
+	illuminant_scale: f64,
+	/// Brightest component of the colors along the third axis of the table,
+	/// denser towards the ends.
+	scale: Vec<f64>,
+	/// Coefficients for every brightest component, then brightness, then the
+	/// other two components relative to the brightest one.
+	coefficients: Vec<[f64; 3]>
+}
+
+impl Debug for Spectral {
+	// the table is long, and follows from the working space anyway
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Spectral").field("working_space", &self.working_space).finish()
+	}
+}
+
+impl Spectral {
+	pub fn new(working_space: ColorSpace) -> Self {
+		let integrate = |step: f64, f: &dyn Fn(f64) -> Color| {
+			let count = ((MAX_WAVELENGTH - MIN_WAVELENGTH) / step) as usize;
+
+			(0..count)
+				.map(|i| f(MIN_WAVELENGTH + (i as f64 + 0.5) * step) * step)
+				.fold(Color::default(), |a, b| a + b)
+		};
+
+		let y_integral = integrate(INTEGRATION_STEP, &|wavelength| cie_xyz(wavelength)).1;
+		let white = integrate(INTEGRATION_STEP, &|wavelength| cie_xyz(wavelength) * d65(wavelength));
+		let illuminant_scale = y_integral / white.1;
+
+		// the matching functions and the table of daylight aren't exact, so
+		// their white is adapted to the working space instead of D65
+		let from_xyz = working_space.from_xyz() * adaptation(white / white.1, working_space.white_xyz());
+
+		let mut spectral = Spectral {
+			working_space,
+			from_xyz,
+			y_integral,
+			illuminant_scale,
+			scale: (0..RESOLUTION).map(|k| smoothstep(smoothstep(k as f64 / (RESOLUTION - 1) as f64))).collect(),
+			coefficients: vec![]
+		};
+
+		spectral.coefficients = spectral.fit_table();
+		spectral
+	}
+
+	fn fit_table(&self) -> Vec<[f64; 3]> {
+		let count = ((MAX_WAVELENGTH - MIN_WAVELENGTH) / FIT_STEP) as usize;
+
+		// what every wavelength adds to the color of a reflectance under daylight
+		let weights: Vec<(f64, Color)> = (0..count).map(|i| {
+			let wavelength = MIN_WAVELENGTH + (i as f64 + 0.5) * FIT_STEP;
+			let xyz = cie_xyz(wavelength) * (d65(wavelength) * self.illuminant_scale * FIT_STEP / self.y_integral);
+
+			(wavelength, self.from_xyz.transform(xyz))
+		}).collect();
+
+		let fit_row = |(channel, j): (usize, usize)| -> Vec<[f64; 3]> {
+			let mut row = vec![[0.; 3]; RESOLUTION * RESOLUTION];
+			let y = j as f64 / (RESOLUTION - 1) as f64;
+
+			for i in 0..RESOLUTION {
+				let x = i as f64 / (RESOLUTION - 1) as f64;
+
+				// neighbouring colors have similar coefficients, so every fit
+				// starts from the last one, going outwards from a middling
+				// brightness where the fit converges easily
+				let start = RESOLUTION / 5;
+
+				let mut fit = |k: usize, coefficients: &mut [f64; 3]| {
+					let z = self.scale[k];
+					let mut rgb = [0.; 3];
+					rgb[channel] = z;
+					rgb[(channel + 1) % 3] = x * z;
+					rgb[(channel + 2) % 3] = y * z;
+
+					let target = Color(rgb[0], rgb[1], rgb[2]);
+					let warm = gauss_newton(&weights, target, coefficients);
+
+					// colors outside of what reflectances can be, like the
+					// primaries of wide spaces, can lead the fit astray, so
+					// it's also tried from scratch
+					let mut cold = [0.; 3];
+
+					if warm > 1e-8 && gauss_newton(&weights, target, &mut cold) < warm {
+						*coefficients = cold;
+					}
+
+					row[k * RESOLUTION + i] = *coefficients;
+				};
+
+				let mut coefficients = [0.; 3];
+
+				for k in start..RESOLUTION {
+					fit(k, &mut coefficients);
+				}
+
+				let mut coefficients = [0.; 3];
+
+				for k in (0..start).rev() {
+					fit(k, &mut coefficients);
+				}
+			}
+
+			row
+		};
+
+		let rows = (0..3).flat_map(|channel| (0..RESOLUTION).map(move |j| (channel, j))).collect::<Vec<_>>();
+
+		#[cfg(not(debug_assertions))]
+			let rows: Vec<Vec<[f64; 3]>> = rows.into_par_iter().map(fit_row).collect();
+
+		#[cfg(debug_assertions)]
+			let rows: Vec<Vec<[f64; 3]>> = rows.into_iter().map(fit_row).collect();
+
+		// rows are by channel and j, and hold k and i, but the table is by
+		// channel, k, j and i
+		let mut table = vec![[0.; 3]; 3 * RESOLUTION * RESOLUTION * RESOLUTION];
+
+		for (index, row) in rows.iter().enumerate() {
+			let (channel, j) = (index / RESOLUTION, index % RESOLUTION);
+
+			for k in 0..RESOLUTION {
+				for i in 0..RESOLUTION {
+					table[((channel * RESOLUTION + k) * RESOLUTION + j) * RESOLUTION + i] = row[k * RESOLUTION + i];
+				}
+			}
+		}
+
+		table
+	}
+
+	// sigmoid coefficients of a color with components between 0 and 1
+	fn lookup(&self, color: Color) -> [f64; 3] {
+		let rgb = [color.0.max(0.).min(1.), color.1.max(0.).min(1.), color.2.max(0.).min(1.)];
+
+		// greys are flat, which the sigmoid only reaches at infinity
+		if rgb[0] == rgb[1] && rgb[1] == rgb[2] {
+			let value = rgb[0];
+
+			return match value {
+				v if v <= 0. => [0., 0., f64::NEG_INFINITY],
+				v if v >= 1. => [0., 0., f64::INFINITY],
+				v => [0., 0., (v - 0.5) / (v * (1. - v)).sqrt()]
+			}
+		}
+
+		let channel = if rgb[0] >= rgb[1] && rgb[0] >= rgb[2] { 0 } else if rgb[1] >= rgb[2] { 1 } else { 2 };
+		let z = rgb[channel];
+		let last = (RESOLUTION - 1) as f64;
+		let x = rgb[(channel + 1) % 3] / z * last;
+		let y = rgb[(channel + 2) % 3] / z * last;
+
+		let k = self.scale.partition_point(|&scale| scale <= z).max(1).min(RESOLUTION - 1) - 1;
+		let dz = (z - self.scale[k]) / (self.scale[k + 1] - self.scale[k]);
+		let i = (x as usize).min(RESOLUTION - 2);
+		let j = (y as usize).min(RESOLUTION - 2);
+		let (dx, dy) = (x - i as f64, y - j as f64);
+
+		let entry = |k: usize, j: usize, i: usize| &self.coefficients[((channel * RESOLUTION + k) * RESOLUTION + j) * RESOLUTION + i];
+
+		let mut coefficients = [0.; 3];
+
+		for (c, coefficient) in coefficients.iter_mut().enumerate() {
+			let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+			let along_x = |k: usize, j: usize| lerp(entry(k, j, i)[c], entry(k, j, i + 1)[c], dx);
+			let along_y = |k: usize| lerp(along_x(k, j), along_x(k, j + 1), dy);
+
+			*coefficient = lerp(along_y(k), along_y(k + 1), dz);
+		}
+
+		coefficients
+	}
+
+	/// Spectrum of something that scales light, like an albedo or a BSDF.
+	/// Colors brighter than 1 are scaled down before fitting them and back up
+	/// afterwards.
+	pub fn reflectance(&self, color: Color, wavelengths: &Wavelengths) -> SampledSpectrum {
+		let max = color.0.max(color.1).max(color.2);
+
+		if max <= 0. {
+			return SampledSpectrum::default()
+		}
+
+		let scale = if max > 1. { 2. * max } else { 1. };
+		let coefficients = self.lookup(color / scale);
+
+		SampledSpectrum(wavelengths.0).map(|_, wavelength| sigmoid(polynomial(&coefficients, wavelength)) * scale)
+	}
+
+	/// Spectrum of light, which is a reflectance lit by daylight.
+	pub fn illuminant(&self, color: Color, wavelengths: &Wavelengths) -> SampledSpectrum {
+		let max = color.0.max(color.1).max(color.2);
+
+		if max <= 0. {
+			return SampledSpectrum::default()
+		}
+
+		// halving keeps the fits away from the extremes
+		let scale = 2. * max;
+		let coefficients = self.lookup(color / scale);
+
+		SampledSpectrum(wavelengths.0).map(|_, wavelength| {
+			sigmoid(polynomial(&coefficients, wavelength)) * scale * d65(wavelength) * self.illuminant_scale
+		})
+	}
+
+	/// Color in the working space of the light carried at `wavelengths`,
+	/// estimated from just them.
+	pub fn to_color(&self, spectrum: SampledSpectrum, wavelengths: &Wavelengths) -> Color {
+		let xyz = wavelengths.0.iter().zip(&spectrum.0)
+			.map(|(&wavelength, &value)| cie_xyz(wavelength) * value)
+			.fold(Color::default(), |a, b| a + b);
+
+		self.from_xyz.transform(xyz / (WAVELENGTHS as f64 * Wavelengths::PDF * self.y_integral))
+	}
+}
+
+fn smoothstep(x: f64) -> f64 {
+	x * x * (3. - 2. * x)
+}
+
+// color of the sigmoid spectrum with `coefficients`, given what every
+// wavelength adds to it
+fn fitted_color(weights: &[(f64, Color)], coefficients: &[f64; 3]) -> Color {
+	weights.iter()
+		.map(|&(wavelength, weight)| weight * sigmoid(polynomial(coefficients, wavelength)))
+		.fold(Color::default(), |a, b| a + b)
+}
+
+// refines `coefficients` until their spectrum has the color `target`, and
+// returns the squared error that's left
+fn gauss_newton(weights: &[(f64, Color)], target: Color, coefficients: &mut [f64; 3]) -> f64 {
+	const EPSILON: f64 = 1e-5;
+
+	let error = |coefficients: &[f64; 3]| {
+		let residual = target - fitted_color(weights, coefficients);
+		residual.0 * residual.0 + residual.1 * residual.1 + residual.2 * residual.2
+	};
+
+	let mut current = error(coefficients);
+
+	for _ in 0..50 {
+		if current < 1e-12 {
+			break
+		}
+
+		let residual = target - fitted_color(weights, coefficients);
+		let mut jacobian = [[0.; 3]; 3];
+
+		for c in 0..3 {
+			let mut above = *coefficients;
+			let mut below = *coefficients;
+			above[c] += EPSILON;
+			below[c] -= EPSILON;
+
+			let derivative = (fitted_color(weights, &above) - fitted_color(weights, &below)) / (2. * EPSILON);
+
+			jacobian[0][c] = derivative.0;
+			jacobian[1][c] = derivative.1;
+			jacobian[2][c] = derivative.2;
+		}
+
+		let step = ColorMatrix(jacobian).inverse().transform(residual);
+
+		if !(step.0.is_finite() && step.1.is_finite() && step.2.is_finite()) {
+			break
+		}
+
+		// full steps can overshoot badly for saturated colors, where the
+		// sigmoid is nearly flat, so they're shortened until they help
+		let mut length = 1.;
+		let mut improved = false;
+
+		for _ in 0..16 {
+			let candidate = [
+				coefficients[0] + step.0 * length,
+				coefficients[1] + step.1 * length,
+				coefficients[2] + step.2 * length
+			];
+
+			let candidate_error = error(&candidate);
+
+			if candidate_error < current {
+				*coefficients = candidate;
+				current = candidate_error;
+				improved = true;
+				break
+			}
+
+			length /= 2.;
+		}
+
+		if !improved {
+			break
+		}
+	}
+
+	current
+}
@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+use crate::math::space::{Pos, Vec3};
+use crate::math::aabb::Aabb;
+use crate::material::albedo::pattern::Pattern;
+
+/// How much of a medium there is at every point, as a multiple of its
+/// coefficients.
+pub trait Density: Send + Sync + Debug {
+	fn density(&self, point: Vec3) -> f64;
+
+	/// Highest density anywhere. Tracking takes steps as if the medium were
+	/// this dense everywhere, so it has to be an upper bound, but the closer it
+	/// is the faster it gets.
+	fn max_density(&self) -> f64;
+}
+
+impl Density for Box<dyn Density> {
+	fn density(&self, point: Vec3) -> f64 {
+		self.as_ref().density(point)
+	}
+
+	fn max_density(&self) -> f64 {
+		self.as_ref().max_density()
+	}
+}
+
+/// The same density everywhere.
+#[derive(Copy, Clone, Debug)]
+pub struct Homogeneous(pub f64);
+
+impl Density for Homogeneous {
+	fn density(&self, _point: Vec3) -> f64 {
+		self.0
+	}
+
+	fn max_density(&self) -> f64 {
+		self.0
+	}
+}
+
+/// Density from a pattern like noise, for smoke and clouds. Pattern values up
+/// to `threshold` are empty, and the ones above it are stretched to go from 0
+/// to `density` at 1, which gives clumps with clear space between them.
+///
+/// Like with `Procedural`, the pattern is evaluated in the space of
+/// `transform`.
+#[derive(Clone, Debug)]
+pub struct PatternDensity<P: Pattern> {
+	pub pattern: P,
+	pub threshold: f64,
+	pub density: f64,
+	pub transform: Pos
+}
+
+impl<P: Pattern> PatternDensity<P> {
+	pub fn new(pattern: P, threshold: f64, density: f64) -> Self {
+		PatternDensity { pattern, threshold, density, transform: Pos::identity() }
+	}
+}
+
+impl<P: Pattern> Density for PatternDensity<P> {
+	fn density(&self, point: Vec3) -> f64 {
+		let value = self.pattern.value(self.transform.inversed() * point);
+		let stretched = (value - self.threshold) / (1. - self.threshold).max(1e-10);
+
+		// patterns are only usually between 0 and 1
+		stretched.max(0.).min(1.) * self.density
+	}
+
+	fn max_density(&self) -> f64 {
+		self.density
+	}
+}
+
+/// Densities on a regular 3D grid stretched over a box, like from a fluid
+/// simulation, and interpolated trilinearly between them. The first sample of
+/// every axis is on the minimum side of the box and the last one on the
+/// maximum side. Outside of the box there's nothing.
+#[derive(Clone, Debug)]
+pub struct Grid {
+	aabb: Aabb,
+	resolution: [usize; 3],
+	/// X changes fastest, then Y, then Z.
+	values: Vec<f64>,
+	max: f64
+}
+
+impl Grid {
+	/// Returns `None` if there aren't `resolution` values, or less than two
+	/// along some axis.
+	pub fn new(aabb: Aabb, resolution: [usize; 3], values: Vec<f64>) -> Option<Self> {
+		if resolution.iter().any(|&r| r < 2) || values.len() != resolution.iter().product() {
+			return None
+		}
+
+		let max = values.iter().fold(0., |max: f64, &value| max.max(value));
+
+		Some(Grid { aabb, resolution, values, max })
+	}
+
+	fn value(&self, x: usize, y: usize, z: usize) -> f64 {
+		self.values[(z * self.resolution[1] + y) * self.resolution[0] + x]
+	}
+}
+
+impl Density for Grid {
+	fn density(&self, point: Vec3) -> f64 {
+		if !self.aabb.contains(point) {
+			return 0.
+		}
+
+		let relative = (point - self.aabb.min) / self.aabb.size();
+		let mut cell = [0; 3];
+		let mut fraction = [0.; 3];
+
+		for (axis, &position) in [relative.x, relative.y, relative.z].iter().enumerate() {
+			let scaled = position * (self.resolution[axis] - 1) as f64;
+			cell[axis] = (scaled.max(0.) as usize).min(self.resolution[axis] - 2);
+			fraction[axis] = scaled - cell[axis] as f64;
+		}
+
+		let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+		let [x, y, z] = cell;
+		let along_x = |y: usize, z: usize| lerp(self.value(x, y, z), self.value(x + 1, y, z), fraction[0]);
+		let along_y = |z: usize| lerp(along_x(y, z), along_x(y + 1, z), fraction[1]);
+
+		lerp(along_y(z), along_y(z + 1), fraction[2]).max(0.)
+	}
+
+	fn max_density(&self) -> f64 {
+		self.max
+	}
+}
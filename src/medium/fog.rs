@@ -0,0 +1,153 @@
+use crate::math::hit::HitResult;
+use crate::math::color::Color;
+use crate::math::ray::Ray;
+use crate::math::space::{Vec2, Vec3};
+use crate::medium::Medium;
+use crate::medium::density::Homogeneous;
+use crate::rng::gen_sampler_once;
+
+/// Medium that fills the whole scene, like haze or smoke in a room, which
+/// every ray travels through. Light from emissive objects shows up in it as
+/// beams, with shafts where something is in the way.
+///
+/// The density is 1 at `height`, and goes down by a factor of e every
+/// 1 / `falloff` going up, and up going down, like exponential height fog. A
+/// `falloff` of 0 makes it the same everywhere. The density of `medium` isn't
+/// used.
+///
+/// Optical depth has a closed form for this, so unlike in a `Volume`,
+/// collisions are found by inverting it directly. The fog is inside of
+/// objects too, which only makes a difference for transparent ones.
+#[derive(Clone, Debug)]
+pub struct Fog {
+	pub medium: Medium<Homogeneous>,
+	pub height: f64,
+	pub falloff: f64
+}
+
+impl Fog {
+	// as far as the density goes either way, which is opaque and clear enough
+	const MAX_EXPONENT: f64 = 50.;
+
+	/// Fog with the same density everywhere.
+	pub fn homogeneous(absorption: Color, scattering: Color) -> Self {
+		Fog { medium: Medium::homogeneous(absorption, scattering), height: 0., falloff: 0. }
+	}
+
+	/// Density at `point`, which stops growing somewhere around e^50 so that
+	/// it stays finite far below `height` even with a large `falloff`.
+	pub fn density(&self, point: Vec3) -> f64 {
+		(-self.falloff * (point.y - self.height)).clamp(-Self::MAX_EXPONENT, Self::MAX_EXPONENT).exp()
+	}
+
+	// integral of the density along `ray` up to `t`, in units of distance
+	fn optical_depth(&self, ray: &Ray, t: f64) -> f64 {
+		let rate = self.falloff * ray.dir.y * t;
+
+		// the fraction that thinning out leaves, which is 1 for level rays
+		let thinning = if rate.abs() < 1e-9 { 1. } else { -(-rate).exp_m1() / rate };
+
+		self.density(ray.pos) * ray.length() * t * thinning
+	}
+
+	// inverse of `optical_depth`, or `None` if there isn't that much fog along
+	// all of the ray's line, which can happen going up
+	fn distance(&self, ray: &Ray, depth: f64) -> Option<f64> {
+		let start = self.density(ray.pos) * ray.length();
+		let rate = self.falloff * ray.dir.y;
+
+		if rate.abs() < 1e-9 {
+			return Some(depth / start)
+		}
+
+		let remaining = 1. - depth * rate / start;
+
+		if remaining > 0. {
+			Some(-remaining.ln() / rate)
+		} else {
+			None
+		}
+	}
+
+	/// Where `ray` collides with the fog before `end`, the `t` of whatever it
+	/// hits otherwise.
+	pub fn ray_trace(&self, ray: &Ray, end: f64) -> Option<HitResult> {
+		let extinction = self.medium.extinction();
+
+		// every channel takes the path of the thickest one, see `Medium`
+		let majorant = extinction.0.max(extinction.1).max(extinction.2);
+
+		if majorant <= 0. {
+			return None
+		}
+
+		let depth = -(1. - gen_sampler_once()).ln() / majorant;
+		let t = self.distance(ray, depth).filter(|&t| t < end)?;
+
+		Some(HitResult {
+			ray: *ray,
+			t,
+			normal: -ray.dir.normalized(),
+			uv: Vec2::default(),
+			material: &self.medium,
+			object: None
+		})
+	}
+
+	/// Fraction of the light that makes it along all of `ray`.
+	pub fn transmittance(&self, ray: &Ray) -> Color {
+		self.attenuation(self.optical_depth(ray, 1.))
+	}
+
+	/// Fraction of the light from infinitely far away in the direction of
+	/// `ray` that makes it to its end, which is what is left of the
+	/// environment there.
+	pub fn transmittance_beyond(&self, ray: &Ray) -> Color {
+		let rate = self.falloff * ray.dir.normalized().y;
+
+		// fog that doesn't thin out that way never ends
+		let depth = if rate > 1e-9 { self.density(ray.at(1.)) / rate } else { f64::INFINITY };
+
+		self.attenuation(depth)
+	}
+
+	// what is left after `depth` of fog, where channels that nothing happens
+	// to stay the same even after infinitely much of it
+	fn attenuation(&self, depth: f64) -> Color {
+		let extinction = self.medium.extinction();
+		let channel = |extinction: f64| if extinction > 0. { (-extinction * depth).exp() } else { 1. };
+
+		Color(channel(extinction.0), channel(extinction.1), channel(extinction.2))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn distance_inverts_optical_depth() {
+		let fog = Fog { height: 1., falloff: 0.5, ..Fog::homogeneous(Color::default(), Color::splat(0.1)) };
+
+		for &dir in &[Vec3::new(3., 0., -4.), Vec3::new(1., 4., -2.), Vec3::new(-2., -3., 1.), Vec3::new(0., 1e-12, 5.)] {
+			let ray = Ray::new(Vec3::new(0.5, 2., 0.), dir);
+
+			for &t in &[0., 0.01, 0.3, 1., 4.] {
+				let depth = fog.optical_depth(&ray, t);
+				let distance = fog.distance(&ray, depth).unwrap();
+
+				assert!((distance - t).abs() < 1e-9, "{:?} at {}: {}", dir, t, distance);
+			}
+		}
+	}
+
+	#[test]
+	fn fog_going_up_can_run_out() {
+		let fog = Fog { height: 0., falloff: 1., ..Fog::homogeneous(Color::default(), Color::splat(0.1)) };
+		let ray = Ray::new(Vec3::new(0., 0., 0.), Vec3::new(0., 1., 0.));
+
+		// all of the fog above is 1 / falloff deep
+		assert!(fog.distance(&ray, 0.99).is_some());
+		assert!(fog.distance(&ray, 1.01).is_none());
+	}
+}
@@ -0,0 +1,132 @@
+use crate::material::{Material, Scatter};
+use crate::material::albedo::Albedo;
+use crate::math::hit::HitResult;
+use crate::math::color::Color;
+use crate::math::ray::Ray;
+use crate::math::space::Vec3;
+use crate::medium::density::{Density, Homogeneous};
+use crate::medium::phase::HenyeyGreenstein;
+use crate::rng::gen_sampler_once;
+
+pub mod density;
+pub mod fog;
+pub mod phase;
+
+fn max_component(color: Color) -> f64 {
+	color.0.max(color.1).max(color.2)
+}
+
+/// What a `Volume` is filled with, like smoke, fog or murky water. Light going
+/// through it is absorbed and scattered with a chance per unit of distance of
+/// the coefficients times the density.
+///
+/// Collisions with a medium are found by `Volume` or `Fog`, which hand them to
+/// their medium to scatter as a `Material`.
+#[derive(Clone, Debug)]
+pub struct Medium<D: Density> {
+	/// Absorption coefficient at a density of 1.
+	pub absorption: Color,
+	/// Scattering coefficient at a density of 1.
+	pub scattering: Color,
+	pub phase: HenyeyGreenstein,
+	pub density: D
+}
+
+impl Medium<Homogeneous> {
+	pub fn homogeneous(absorption: Color, scattering: Color) -> Self {
+		Medium { absorption, scattering, phase: HenyeyGreenstein::default(), density: Homogeneous(1.) }
+	}
+}
+
+impl<D: Density> Medium<D> {
+	/// Extinction coefficient at a density of 1, which is how much light is
+	/// lost to absorption and to being scattered elsewhere.
+	pub fn extinction(&self) -> Color {
+		self.absorption + self.scattering
+	}
+
+	/// Extinction coefficient at `point`.
+	pub fn extinction_at(&self, point: Vec3) -> Color {
+		self.extinction() * self.density.density(point)
+	}
+
+	/// Largest extinction coefficient of any channel anywhere in the medium.
+	pub fn majorant(&self) -> f64 {
+		max_component(self.extinction()) * self.density.max_density()
+	}
+
+	// collisions are found with the extinction of the thickest channel, so
+	// that all channels take the same path. At every one of them, the thinner
+	// channels have a chance of passing straight through instead. Returns how
+	// much of the light is scattered, how much passes through, and the chance
+	// of picking scattering, which are all the same everywhere
+	fn collision(&self) -> (Color, Color, f64) {
+		let extinction = self.extinction();
+		let max = max_component(extinction);
+
+		let scattered = self.scattering / max;
+		let passed = (Color::splat(max) - extinction) / max;
+		let chance = max_component(scattered) / (max_component(scattered) + max_component(passed));
+
+		(scattered, passed, chance)
+	}
+}
+
+impl<D: Density> Albedo for Medium<D> {
+	// the single-scattering albedo
+	fn albedo(&self, _result: HitResult) -> Color {
+		let extinction = self.extinction();
+
+		Color(
+			self.scattering.0 / extinction.0.max(1e-10),
+			self.scattering.1 / extinction.1.max(1e-10),
+			self.scattering.2 / extinction.2.max(1e-10)
+		)
+	}
+}
+
+impl<D: Density> Material for Medium<D> {
+	fn sample(&self, result: HitResult) -> Option<Scatter> {
+		let (scattered, passed, chance) = self.collision();
+
+		// a grey medium that doesn't scatter
+		if !(chance > 0. || max_component(passed) > 0.) {
+			return None
+		}
+
+		let remaining = result.ray.from(result.t);
+
+		if gen_sampler_once() < chance {
+			let incoming = result.ray.dir.normalized();
+			let direction = self.phase.sample(incoming);
+
+			Some(Scatter {
+				ray: Ray::new(remaining.pos, direction * remaining.length()).with_time(result.ray.time),
+				// the phase function is sampled exactly
+				weight: scattered / chance,
+				pdf: Some(self.phase.eval(incoming, direction)),
+				passes_through: false,
+				wavelength_dependent: false,
+				null: false
+			})
+		} else {
+			Some(Scatter { passes_through: true, null: true, ..Scatter::specular(remaining, passed / (1. - chance)) })
+		}
+	}
+
+	// light is only sampled when the collision scatters, so it's made up for
+	// like in `sample`
+	fn eval(&self, result: HitResult, dir: Vec3) -> Color {
+		let (scattered, _, chance) = self.collision();
+
+		if chance <= 0. {
+			return Color::default()
+		}
+
+		scattered * (self.phase.eval(result.ray.dir.normalized(), dir) / chance)
+	}
+
+	fn pdf(&self, result: HitResult, dir: Vec3) -> f64 {
+		self.phase.eval(result.ray.dir.normalized(), dir)
+	}
+}
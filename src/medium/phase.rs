@@ -0,0 +1,51 @@
+use std::f64::consts::PI;
+use crate::math::space::Vec3;
+use crate::light::basis;
+use crate::rng::gen_sampler_once;
+
+/// Henyey-Greenstein phase function, which says where light goes when it's
+/// scattered by a medium. `g` is the average cosine of how far it's turned,
+/// between -1 and 1: positive values scatter forwards like haze and clouds,
+/// negative ones backwards, and 0 in every direction alike.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HenyeyGreenstein {
+	pub g: f64
+}
+
+impl HenyeyGreenstein {
+	pub fn new(g: f64) -> Self {
+		// a g of 1 or -1 is a delta, which can't be evaluated
+		HenyeyGreenstein { g: g.max(-0.999).min(0.999) }
+	}
+
+	/// Density of light travelling along the normalized direction `incoming`
+	/// being scattered towards the normalized direction `dir`, with respect to
+	/// solid angle. The phase function integrates to 1, so this is also the
+	/// density with which `sample` picks `dir`.
+	pub fn eval(&self, incoming: Vec3, dir: Vec3) -> f64 {
+		let g = self.g;
+		let denominator = 1. + g * g - 2. * g * incoming.dot(dir);
+
+		(1. - g * g) / (4. * PI * denominator * denominator.max(0.).sqrt())
+	}
+
+	/// Picks a direction for light travelling along `incoming` to be scattered
+	/// towards.
+	pub fn sample(&self, incoming: Vec3) -> Vec3 {
+		let g = self.g;
+		let u = gen_sampler_once();
+
+		let cos_theta = if g.abs() < 1e-3 {
+			1. - 2. * u
+		} else {
+			let s = (1. - g * g) / (1. - g + 2. * g * u);
+			(1. + g * g - s * s) / (2. * g)
+		}.max(-1.).min(1.);
+
+		let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+		let phi = 2. * PI * gen_sampler_once();
+		let (u, v) = basis(incoming);
+
+		u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin()) + incoming * cos_theta
+	}
+}
@@ -1,43 +1,304 @@
+use std::io::{self, BufRead, Seek, SeekFrom, Write};
 use crate::math::color::Color;
-use crate::math::space::Vec3;
-use ultraviolet::Lerp;
+use crate::material::albedo::procedural::ColorRamp;
+use crate::aov::{Aov, Aovs, Frame, Accumulation};
+use crate::image::{invalid_data, read_line};
 
+/// Added to the brightness of a pixel when estimating its relative error, so
+/// that nearly black pixels don't need forever to converge.
+const ERROR_FLOOR: f64 = 0.01;
+
+/// Average of many renders of the same image, including its AOVs.
 pub struct ProgressiveBuffer {
-	combined: Vec<(Color, Color, Vec3)>,
+	width: usize,
+	height: usize,
+	frame: Frame,
+	/// Where every AOV is in a pixel, how many values it has and how they are
+	/// combined.
+	layout: Vec<(usize, usize, Accumulation)>,
+	/// Number of samples in every pixel.
+	samples: Vec<u64>,
+	/// Sum of squared differences from the mean color, for Welford's variance
+	/// algorithm.
+	m2: Vec<Color>,
 	spp: u64
 }
 
 impl ProgressiveBuffer {
-	pub fn new(width: usize, height: usize) -> Self {
+	pub fn new(width: usize, height: usize, aovs: Aovs) -> Self {
+		let layout = aovs.list().iter()
+			.map(|&aov| (aovs.offset(aov).unwrap(), aov.channels().len(), aov.accumulation()))
+			.collect();
+
 		Self {
-			combined: vec![Default::default(); width * height],
+			width,
+			height,
+			frame: Frame::new(aovs, width * height),
+			layout,
+			samples: vec![0; width * height],
+			m2: vec![Color::default(); width * height],
 			spp: 0
 		}
 	}
 
-	pub fn update<T: AsRef<[(Color, Color, Vec3)]>>(&mut self, update: T) {
-		let update = update.as_ref();
-		assert_eq!(update.len(), self.combined.len(), "Buffer has an incorrect size!");
+	/// Adds one sample to every pixel. The update has to have the same AOVs.
+	pub fn update(&mut self, update: &Frame) {
+		assert_eq!(update.len(), self.frame.len(), "Buffer has an incorrect size!");
+		assert_eq!(update.aovs(), self.frame.aovs(), "Buffer has different AOVs!");
+
+		self.spp += 1;
+
+		for index in 0..update.len() {
+			self.add(index, update.pixel(index));
+		}
+	}
+
+	/// Adds one sample to each of `pixels`, like the ones rendered by
+	/// `Camera::render_pixels`.
+	pub fn update_pixels(&mut self, pixels: &[usize], update: &Frame) {
+		assert_eq!(update.len(), pixels.len(), "Buffer has an incorrect size!");
+		assert_eq!(update.aovs(), self.frame.aovs(), "Buffer has different AOVs!");
 
 		self.spp += 1;
-		let t = 1. / (self.spp as f64);
 
-		self.combined.iter_mut()
-			.zip(update)
-			.for_each(|(t1, t2)| {
-				t1.0.lerp_mut(&t2.0, t);
-				t1.1.lerp_mut(&t2.1, t);
-				t1.2 = t1.2.lerp(t2.2, t);
-			});
+		for (i, &index) in pixels.iter().enumerate() {
+			self.add(index, update.pixel(i));
+		}
+	}
+
+	fn add(&mut self, index: usize, sample: &[f64]) {
+		self.samples[index] += 1;
+		let n = self.samples[index];
+		let t = 1. / n as f64;
+
+		let old = self.frame.color(index);
+		let new = Color(sample[0], sample[1], sample[2]);
+		let mean = old + (new - old) * t;
+
+		self.m2[index] += (new - old) * (new - mean);
+
+		let pixel = self.frame.pixel_mut(index);
+		pixel[..3].copy_from_slice(&[mean.0, mean.1, mean.2]);
+
+		for &(offset, len, accumulation) in &self.layout {
+			let range = offset..offset + len;
+
+			for (value, &sample) in pixel[range.clone()].iter_mut().zip(&sample[range]) {
+				*value = match accumulation {
+					Accumulation::Average => *value + (sample - *value) * t,
+					Accumulation::Min if n > 1 => value.min(sample),
+					Accumulation::First if n > 1 => *value,
+					Accumulation::Min | Accumulation::First => sample,
+					Accumulation::Sum => *value + sample
+				};
+			}
+		}
+	}
+
+	pub fn frame(&self) -> &Frame {
+		&self.frame
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
 	}
 
+	/// Number of updates so far. With adaptive sampling, some pixels have
+	/// fewer samples than this, see `samples`.
 	pub fn spp(&self) -> u64 {
 		self.spp
 	}
+
+	pub fn samples(&self) -> &[u64] {
+		&self.samples
+	}
+
+	/// Sample variance of the color of a pixel.
+	pub fn variance(&self, index: usize) -> Color {
+		match self.samples[index] {
+			0 | 1 => Color::default(),
+			n => self.m2[index] / (n - 1) as f64
+		}
+	}
+
+	/// Estimated relative error of the brightness of a pixel, which is the
+	/// standard error of the mean divided by the mean. Pixels with fewer than
+	/// two samples have an infinite error.
+	pub fn error(&self, index: usize) -> f64 {
+		let n = self.samples[index];
+
+		if n < 2 {
+			return f64::INFINITY
+		}
+
+		let standard_error = (self.variance(index).luminance().max(0.) / n as f64).sqrt();
+		standard_error / (self.frame.color(index).luminance().max(0.) + ERROR_FLOOR)
+	}
+
+	/// Pixels that have fewer than `min_samples` samples or an error above
+	/// `threshold`, which are the ones worth rendering more of.
+	pub fn pending(&self, threshold: f64, min_samples: u64) -> Vec<usize> {
+		(0..self.frame.len())
+			.filter(|&index| self.samples[index] < min_samples.max(2) || self.error(index) > threshold)
+			.collect()
+	}
+
+	/// Whether every pixel has at least `min_samples` samples and an error
+	/// below `threshold`.
+	pub fn is_converged(&self, threshold: f64, min_samples: u64) -> bool {
+		(0..self.frame.len())
+			.all(|index| self.samples[index] >= min_samples.max(2) && self.error(index) <= threshold)
+	}
+
+	/// Colors every pixel by its error: blue is well below `threshold`, green is
+	/// at the threshold, and yellow to red is up to twice the threshold and
+	/// beyond.
+	pub fn error_heatmap(&self, threshold: f64) -> Vec<Color> {
+		let ramp = ColorRamp::new(vec![
+			(0., Color(0., 0., 0.2)),
+			(0.5, Color(0., 0.4, 1.)),
+			(1., Color(0., 1., 0.)),
+			(1.5, Color(1., 1., 0.)),
+			(2., Color(1., 0., 0.))
+		]);
+
+		(0..self.frame.len())
+			.map(|index| ramp.color(self.error(index) / threshold))
+			.collect()
+	}
+
+	/// Writes everything accumulated so far, so that `read` can pick up where
+	/// this left off. A short text header with the size, `spp` and AOVs is
+	/// followed by every pixel as little endian doubles.
+	pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		let names: Vec<&str> = self.frame.aovs().list().iter().map(|aov| aov.name()).collect();
+		write!(writer, "{} {}\n{}\n{}\n", self.width, self.height, self.spp, names.join(","))?;
+
+		for index in 0..self.frame.len() {
+			writer.write_all(&self.samples[index].to_le_bytes())?;
+
+			let m2 = self.m2[index];
+
+			for value in [m2.0, m2.1, m2.2].iter().chain(self.frame.pixel(index)) {
+				writer.write_all(&value.to_le_bytes())?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Reads a buffer written by `write`. The size in the header is checked
+	/// against what's left of `reader` before anything is allocated for it.
+	pub fn read<R: BufRead + Seek>(mut reader: R) -> io::Result<Self> {
+		let size = read_line(&mut reader)?;
+		let (width, height) = match size.split(' ').map(str::parse).collect::<Result<Vec<usize>, _>>().as_deref() {
+			Ok(&[width, height]) => (width, height),
+			_ => return Err(invalid_data("invalid size"))
+		};
+
+		let spp = read_line(&mut reader)?.parse().map_err(|_| invalid_data("invalid spp"))?;
+
+		let names = read_line(&mut reader)?;
+		let aovs = names.split(',')
+			.filter(|name| !name.is_empty())
+			.map(|name| Aov::from_name(name).ok_or_else(|| invalid_data(format!("unknown AOV {}", name))))
+			.collect::<io::Result<Vec<_>>>()?;
+
+		let aovs = Aovs::new(&aovs);
+
+		// every pixel has its sample count and variance before the values
+		let pixel_len = 8 * (4 + aovs.stride()) as u64;
+		let position = reader.stream_position()?;
+		let remaining = reader.seek(SeekFrom::End(0))?.saturating_sub(position);
+		reader.seek(SeekFrom::Start(position))?;
+
+		let len = width.checked_mul(height)
+			.filter(|&pixels| pixels > 0)
+			.and_then(|pixels| (pixels as u64).checked_mul(pixel_len))
+			.ok_or_else(|| invalid_data("invalid size"))?;
+
+		if len > remaining {
+			return Err(invalid_data("size doesn't match the data"))
+		}
+
+		let mut buffer = ProgressiveBuffer::new(width, height, aovs);
+		buffer.spp = spp;
+
+		let mut bytes = [0u8; 8];
+
+		let mut next = |reader: &mut R| -> io::Result<[u8; 8]> {
+			reader.read_exact(&mut bytes)?;
+			Ok(bytes)
+		};
+
+		for index in 0..buffer.frame.len() {
+			buffer.samples[index] = u64::from_le_bytes(next(&mut reader)?);
+
+			let mut m2 = [0.; 3];
+
+			for value in m2.iter_mut().chain(buffer.frame.pixel_mut(index)) {
+				*value = f64::from_le_bytes(next(&mut reader)?);
+			}
+
+			buffer.m2[index] = Color(m2[0], m2[1], m2[2]);
+		}
+
+		Ok(buffer)
+	}
 }
 
-impl AsRef<[(Color, Color, Vec3)]> for ProgressiveBuffer {
-	fn as_ref(&self) -> &[(Color, Color, Vec3)] {
-		&self.combined
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+
+	// a few samples of made up values, different for every pixel and channel
+	fn buffer() -> ProgressiveBuffer {
+		let mut buffer = ProgressiveBuffer::new(3, 2, Aovs::all());
+
+		for sample in 0..3 {
+			let mut frame = Frame::new(Aovs::all(), 6);
+
+			for (index, pixel) in frame.pixels_mut().enumerate() {
+				for (channel, value) in pixel.iter_mut().enumerate() {
+					*value = (index * 7 + channel * 3 + sample) as f64 * 0.1;
+				}
+			}
+
+			buffer.update(&frame);
+		}
+
+		buffer
+	}
+
+	#[test]
+	fn reads_what_it_writes() {
+		let buffer = buffer();
+		let mut file = vec![];
+		buffer.write(&mut file).unwrap();
+
+		let read = ProgressiveBuffer::read(Cursor::new(&file)).unwrap();
+
+		assert_eq!((read.width(), read.height(), read.spp()), (3, 2, 3));
+		assert_eq!(read.frame.aovs(), buffer.frame.aovs());
+		assert_eq!(read.samples, buffer.samples);
+
+		for index in 0..6 {
+			assert_eq!(read.frame.pixel(index), buffer.frame.pixel(index));
+			assert_eq!(read.variance(index), buffer.variance(index));
+		}
+	}
+
+	#[test]
+	fn rejects_missing_data() {
+		let mut file = vec![];
+		buffer().write(&mut file).unwrap();
+		file.pop();
+
+		assert!(ProgressiveBuffer::read(Cursor::new(&file)).is_err());
 	}
 }
@@ -0,0 +1,144 @@
+use crate::rng::{Sampler, Independent, Sobol, hash};
+
+const SIZE: usize = 64;
+const SIGMA: f64 = 1.5;
+
+/// Blue noise over the image and a Sobol sequence over the samples. Every
+/// pixel takes the same Sobol sequence and shifts it by a value from a tiled
+/// blue noise mask, which every dimension reads at its own random offset.
+/// Neighbouring pixels get very different numbers, which turns the noise of
+/// low sample counts into a fine grain that's easier on the eye and easier to
+/// denoise.
+#[derive(Clone, Debug)]
+pub struct BlueNoise {
+	/// Values between 0 and 1, as a `SIZE` by `SIZE` tile.
+	mask: Vec<f64>,
+	sobol: Sobol
+}
+
+impl BlueNoise {
+	/// Makes a mask with the void-and-cluster method, which takes a moment.
+	pub fn new() -> Self {
+		let ranks = void_and_cluster();
+
+		BlueNoise {
+			mask: ranks.iter().map(|&rank| (rank as f64 + 0.5) / (SIZE * SIZE) as f64).collect(),
+			sobol: Sobol::new()
+		}
+	}
+}
+
+impl Default for BlueNoise {
+	fn default() -> Self {
+		BlueNoise::new()
+	}
+}
+
+impl Sampler for BlueNoise {
+	fn get(&self, seed: u64, pixel: (usize, usize), index: u64, dimension: u32) -> f64 {
+		let offset = hash(&[seed, dimension as u64]);
+
+		let x = (pixel.0 + (offset as usize % SIZE)) % SIZE;
+		let y = (pixel.1 + ((offset >> 32) as usize % SIZE)) % SIZE;
+
+		(self.mask[y * SIZE + x] + self.sobol.scrambled(seed, index, dimension)).fract()
+	}
+}
+
+// Ulichney's void-and-cluster: starting from a few random points, points are
+// added one at a time into the biggest gap between the others, and the order
+// they are added in becomes the mask. Distances wrap around, so the mask tiles.
+fn void_and_cluster() -> Vec<usize> {
+	let count = SIZE * SIZE;
+
+	// energy contributed by a point to everything around it
+	let mut kernel = vec![0.; count];
+
+	for y in 0..SIZE {
+		for x in 0..SIZE {
+			let dx = x.min(SIZE - x) as f64;
+			let dy = y.min(SIZE - y) as f64;
+			kernel[y * SIZE + x] = (-(dx * dx + dy * dy) / (2. * SIGMA * SIGMA)).exp();
+		}
+	}
+
+	let mut points = vec![false; count];
+	let mut energy = vec![0.; count];
+
+	let toggle = |points: &mut Vec<bool>, energy: &mut Vec<f64>, index: usize| {
+		points[index] = !points[index];
+		let sign = if points[index] { 1. } else { -1. };
+		let (px, py) = (index % SIZE, index / SIZE);
+
+		for y in 0..SIZE {
+			for x in 0..SIZE {
+				let k = ((y + SIZE - py) % SIZE) * SIZE + (x + SIZE - px) % SIZE;
+				energy[y * SIZE + x] += sign * kernel[k];
+			}
+		}
+	};
+
+	// the point with the most energy around it, or the gap with the least
+	let extreme = |points: &Vec<bool>, energy: &Vec<f64>, set: bool| -> usize {
+		let candidates = (0..count).filter(|&i| points[i] == set);
+
+		if set {
+			candidates.max_by(|&a, &b| energy[a].partial_cmp(&energy[b]).unwrap()).unwrap()
+		} else {
+			candidates.min_by(|&a, &b| energy[a].partial_cmp(&energy[b]).unwrap()).unwrap()
+		}
+	};
+
+	// random initial points, always the same ones
+	let initial = count / 10;
+
+	for i in 0.. {
+		if points.iter().filter(|&&p| p).count() == initial {
+			break
+		}
+
+		let index = (Independent.get(0, (0, 0), i, 0) * count as f64) as usize;
+
+		if !points[index] {
+			toggle(&mut points, &mut energy, index);
+		}
+	}
+
+	// spread them out by moving the most crowded point into the biggest gap,
+	// until that doesn't change anything
+	loop {
+		let cluster = extreme(&points, &energy, true);
+		toggle(&mut points, &mut energy, cluster);
+		let void = extreme(&points, &energy, false);
+
+		if void == cluster {
+			toggle(&mut points, &mut energy, cluster);
+			break
+		}
+
+		toggle(&mut points, &mut energy, void);
+	}
+
+	let mut ranks = vec![0; count];
+
+	// rank the initial points by taking away the most crowded one each time
+	{
+		let mut points = points.clone();
+		let mut energy = energy.clone();
+
+		for rank in (0..initial).rev() {
+			let cluster = extreme(&points, &energy, true);
+			toggle(&mut points, &mut energy, cluster);
+			ranks[cluster] = rank;
+		}
+	}
+
+	// and the rest by filling in the biggest gap each time
+	for rank in initial..count {
+		let void = extreme(&points, &energy, false);
+		toggle(&mut points, &mut energy, void);
+		ranks[void] = rank;
+	}
+
+	ranks
+}
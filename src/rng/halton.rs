@@ -0,0 +1,55 @@
+use crate::rng::{Sampler, hash, permute};
+
+const PRIMES: [u64; 16] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+
+/// Halton sequence, where dimension `d` is the radical inverse of the sample
+/// index in the `d`th prime base. Every pixel gets its own Owen scrambling,
+/// which keeps the sequence well spread out but stops neighbouring pixels
+/// from sharing their patterns.
+///
+/// Large bases are badly distributed for the first few samples, so only the
+/// first 16 primes are used, and higher dimensions start over with them at a
+/// different offset into the sequence.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Halton;
+
+impl Sampler for Halton {
+	fn get(&self, seed: u64, pixel: (usize, usize), index: u64, dimension: u32) -> f64 {
+		let base = PRIMES[dimension as usize % PRIMES.len()];
+		let group = dimension as u64 / PRIMES.len() as u64;
+
+		let offset = match group {
+			0 => 0,
+			_ => hash(&[seed, pixel.0 as u64, pixel.1 as u64, group]) & 0xfffff
+		};
+
+		let scramble = hash(&[seed, pixel.0 as u64, pixel.1 as u64, dimension as u64, 1]);
+		scrambled_radical_inverse(index + offset, base, scramble)
+	}
+}
+
+// mirrors the digits of `index` around the decimal point, with Owen
+// scrambling: every digit is shuffled by a random permutation that depends on
+// all the digits before it. Trailing zero digits are shuffled too, otherwise
+// the scrambling would leave a pattern.
+fn scrambled_radical_inverse(mut index: u64, base: u64, scramble: u64) -> f64 {
+	let inv_base = 1. / base as f64;
+	let mut scale = inv_base;
+	let mut result = 0.;
+	let mut prefix = 0u64;
+	let mut digit_index = 0;
+
+	while scale > 1e-16 {
+		let digit = index % base;
+		let permutation = hash(&[scramble, digit_index, prefix]) as u32;
+
+		result += permute(digit as u32, base as u32, permutation) as f64 * scale;
+
+		prefix = prefix.wrapping_mul(base).wrapping_add(digit);
+		index /= base;
+		scale *= inv_base;
+		digit_index += 1;
+	}
+
+	result.min(1. - f64::EPSILON / 2.)
+}
@@ -1,5 +1,11 @@
-mod small;
-mod wyrand;
+mod sampler;
+mod stratified;
+mod halton;
+mod sobol;
+mod blue_noise;
 
-pub use small::*;
-pub use wyrand::*;
+pub use sampler::*;
+pub use stratified::*;
+pub use halton::*;
+pub use sobol::*;
+pub use blue_noise::*;
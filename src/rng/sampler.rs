@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+thread_local!(
+	static PATH_SAMPLER: RefCell<PathSampler> = RefCell::new(PathSampler::new(Arc::new(Independent), 0, (0, 0), 0));
+);
+
+/// Number of dimensions used by the camera: two for the position in the
+/// pixel, two for the lens and one for the time, plus some spare ones. The
+/// first spare one picks the wavelength when rendering spectrally.
+pub const CAMERA_DIMENSIONS: u32 = 8;
+
+/// Number of dimensions every bounce of a path gets. Anything a bounce draws
+/// beyond that comes from independent random numbers instead.
+pub const BOUNCE_DIMENSIONS: u32 = 16;
+
+/// Source of the random numbers of a render. A sampler is a pure function of
+/// the seed, the pixel, the sample index and the dimension, so renders are the
+/// same no matter which thread traces which path.
+///
+/// Dimensions are used the same way by every sample: the camera uses the first
+/// `CAMERA_DIMENSIONS`, and then every bounce gets `BOUNCE_DIMENSIONS`. That's
+/// what lets low-discrepancy samplers spread the samples of each decision
+/// evenly.
+pub trait Sampler: Send + Sync + Debug {
+	/// Number in [0, 1).
+	fn get(&self, seed: u64, pixel: (usize, usize), index: u64, dimension: u32) -> f64;
+}
+
+/// White noise, where every number is independent of all others.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Independent;
+
+impl Sampler for Independent {
+	fn get(&self, seed: u64, pixel: (usize, usize), index: u64, dimension: u32) -> f64 {
+		to_unit(hash(&[seed, pixel.0 as u64, pixel.1 as u64, index, dimension as u64]))
+	}
+}
+
+/// Walks through the dimensions of one sample of one pixel.
+#[derive(Clone, Debug)]
+pub struct PathSampler {
+	sampler: Arc<dyn Sampler>,
+	seed: u64,
+	pixel: (usize, usize),
+	index: u64,
+	dimension: u32,
+	end: u32,
+	overflow: u32
+}
+
+impl PathSampler {
+	/// Starts at the first camera dimension.
+	pub fn new(sampler: Arc<dyn Sampler>, seed: u64, pixel: (usize, usize), index: u64) -> Self {
+		PathSampler { sampler, seed, pixel, index, dimension: 0, end: CAMERA_DIMENSIONS, overflow: 0 }
+	}
+
+	/// Moves on to the dimensions of bounce number `bounce`, counting from 0.
+	/// Dimensions that were already used are never handed out again.
+	pub fn start_bounce(&mut self, bounce: u32) {
+		let start = CAMERA_DIMENSIONS + bounce * BOUNCE_DIMENSIONS;
+
+		self.dimension = self.dimension.max(start);
+		self.end = start + BOUNCE_DIMENSIONS;
+	}
+
+	pub fn next_f64(&mut self) -> f64 {
+		if self.dimension < self.end {
+			self.dimension += 1;
+			self.sampler.get(self.seed, self.pixel, self.index, self.dimension - 1)
+		} else {
+			// far away from any dimension a bounce would use
+			self.overflow += 1;
+			Independent.get(self.seed, self.pixel, self.index, u32::MAX - self.overflow)
+		}
+	}
+
+	pub fn range(&mut self, min: f64, max: f64) -> f64 {
+		self.next_f64().mul_add(max - min, min)
+	}
+}
+
+/// Makes `sampler` the one that `gen_sampler_once` draws from on this thread,
+/// usually right before tracing a path.
+pub fn set_sampler(sampler: PathSampler) {
+	PATH_SAMPLER.with(|c| *c.borrow_mut() = sampler);
+}
+
+/// See `PathSampler::start_bounce`.
+pub fn start_bounce(bounce: u32) {
+	PATH_SAMPLER.with(|c| c.borrow_mut().start_bounce(bounce));
+}
+
+/// Next number from the current sampler of this thread.
+pub fn gen_sampler_once() -> f64 {
+	PATH_SAMPLER.with(|c| c.borrow_mut().next_f64())
+}
+
+pub fn gen_sampler_range(min: f64, max: f64) -> f64 {
+	min + (max - min) * gen_sampler_once()
+}
+
+/// Mixes all of `values` into one well distributed number.
+pub(crate) fn hash(values: &[u64]) -> u64 {
+	values.iter().fold(0x243f6a8885a308d3, |state, &value| mix(state ^ value))
+}
+
+// splitmix64 finalizer
+fn mix(mut x: u64) -> u64 {
+	x = x.wrapping_add(0x9e3779b97f4a7c15);
+	x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+	x ^ (x >> 31)
+}
+
+/// Turns random bits into a number in [0, 1). The top 53 bits fill the
+/// mantissa exactly, so this never rounds up to 1.
+pub(crate) fn to_unit(bits: u64) -> f64 {
+	(bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Same as `to_unit`, for 32 bits.
+pub(crate) fn to_unit_u32(bits: u32) -> f64 {
+	bits as f64 / (1u64 << 32) as f64
+}
@@ -0,0 +1,124 @@
+use crate::rng::{Sampler, hash, to_unit_u32};
+
+// degree, coefficients and initial direction numbers of the primitive
+// polynomials for dimensions 1 to 15, from Joe and Kuo. Dimension 0 is the van
+// der Corput sequence.
+const POLYNOMIALS: [(u32, u32, &[u32]); 15] = [
+	(1, 0, &[1]),
+	(2, 1, &[1, 3]),
+	(3, 1, &[1, 3, 1]),
+	(3, 2, &[1, 1, 1]),
+	(4, 1, &[1, 1, 3, 3]),
+	(4, 4, &[1, 3, 5, 13]),
+	(5, 2, &[1, 1, 5, 5, 17]),
+	(5, 4, &[1, 1, 5, 5, 5]),
+	(5, 7, &[1, 1, 7, 11, 19]),
+	(5, 11, &[1, 1, 5, 1, 1]),
+	(5, 13, &[1, 1, 1, 3, 11]),
+	(5, 14, &[1, 3, 5, 5, 31]),
+	(6, 1, &[1, 3, 3, 9, 7, 49]),
+	(6, 13, &[1, 1, 1, 15, 21, 21]),
+	(6, 16, &[1, 3, 1, 13, 27, 49])
+];
+
+const DIMENSIONS: usize = POLYNOMIALS.len() + 1;
+
+/// Sobol sequence with Owen scrambling, using the hash based scrambling from
+/// Burley's "Practical Hash-based Owen Scrambling". Every pixel shuffles the
+/// order of its samples and scrambles every dimension differently.
+///
+/// Only the first 16 dimensions have their own direction numbers. Higher ones
+/// reuse them with a different shuffle, which keeps every dimension evenly
+/// spread out on its own.
+#[derive(Clone, Debug)]
+pub struct Sobol {
+	matrices: Vec<[u32; 32]>
+}
+
+impl Sobol {
+	pub fn new() -> Self {
+		let mut matrices = vec![[0; 32]; DIMENSIONS];
+
+		for (bit, direction) in matrices[0].iter_mut().enumerate() {
+			*direction = 1 << (31 - bit);
+		}
+
+		for (dimension, &(degree, coefficients, initial)) in POLYNOMIALS.iter().enumerate() {
+			let directions = &mut matrices[dimension + 1];
+			let degree = degree as usize;
+
+			for bit in 0..32 {
+				directions[bit] = if bit < degree {
+					initial[bit] << (31 - bit)
+				} else {
+					let mut direction = directions[bit - degree] ^ (directions[bit - degree] >> degree);
+
+					for k in 1..degree {
+						if (coefficients >> (degree - 1 - k)) & 1 == 1 {
+							direction ^= directions[bit - k];
+						}
+					}
+
+					direction
+				};
+			}
+		}
+
+		Sobol { matrices }
+	}
+
+	fn sample(&self, index: u32, dimension: usize) -> u32 {
+		let mut result = 0;
+		let mut index = index;
+		let mut bit = 0;
+
+		while index != 0 {
+			if index & 1 == 1 {
+				result ^= self.matrices[dimension][bit];
+			}
+
+			index >>= 1;
+			bit += 1;
+		}
+
+		result
+	}
+
+	/// Dimension `dimension` of sample `index`, shuffled and scrambled by
+	/// `seed`.
+	pub(crate) fn scrambled(&self, seed: u64, index: u64, dimension: u32) -> f64 {
+		let group = dimension as u64 / DIMENSIONS as u64;
+
+		let shuffled = nested_uniform_scramble(index as u32, hash(&[seed, group]) as u32);
+		let value = self.sample(shuffled, dimension as usize % DIMENSIONS);
+
+		to_unit_u32(nested_uniform_scramble(value, hash(&[seed, dimension as u64, 1]) as u32))
+	}
+}
+
+impl Default for Sobol {
+	fn default() -> Self {
+		Sobol::new()
+	}
+}
+
+impl Sampler for Sobol {
+	fn get(&self, seed: u64, pixel: (usize, usize), index: u64, dimension: u32) -> f64 {
+		self.scrambled(hash(&[seed, pixel.0 as u64, pixel.1 as u64]), index, dimension)
+	}
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+	x = x.wrapping_add(seed);
+	x ^= x.wrapping_mul(0x6c50b47c);
+	x ^= x.wrapping_mul(0xb82f1e52);
+	x ^= x.wrapping_mul(0xc7afe638);
+	x ^= x.wrapping_mul(0x8d22f6e6);
+	x
+}
+
+// Owen scrambling flips every bit based on the bits above it, while the
+// permutation works the other way around
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+	laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
@@ -0,0 +1,67 @@
+use crate::rng::{Sampler, hash, to_unit};
+
+/// Splits every dimension into `samples` equal strata and puts one jittered
+/// sample into each of them, in a different random order for every pixel and
+/// dimension. Once more than `samples` samples are taken, it starts over with
+/// new orders.
+#[derive(Copy, Clone, Debug)]
+pub struct Stratified {
+	pub samples: u32
+}
+
+impl Stratified {
+	pub fn new(samples: u32) -> Self {
+		Stratified { samples: samples.max(1) }
+	}
+}
+
+impl Sampler for Stratified {
+	fn get(&self, seed: u64, pixel: (usize, usize), index: u64, dimension: u32) -> f64 {
+		let n = self.samples as u64;
+		let round = index / n;
+		let key = hash(&[seed, pixel.0 as u64, pixel.1 as u64, round, dimension as u64]);
+
+		let stratum = permute((index % n) as u32, self.samples, key as u32);
+		let jitter = to_unit(hash(&[key, index]));
+
+		(stratum as f64 + jitter) / n as f64
+	}
+}
+
+/// Random permutation of `0..len` picked by `seed`, from Kensler's
+/// "Correlated Multi-Jittered Sampling".
+pub(crate) fn permute(mut i: u32, len: u32, seed: u32) -> u32 {
+	let mut w = len - 1;
+	w |= w >> 1;
+	w |= w >> 2;
+	w |= w >> 4;
+	w |= w >> 8;
+	w |= w >> 16;
+
+	loop {
+		i ^= seed;
+		i = i.wrapping_mul(0xe170893d);
+		i ^= seed >> 16;
+		i ^= (i & w) >> 4;
+		i ^= seed >> 8;
+		i = i.wrapping_mul(0x0929eb3f);
+		i ^= seed >> 23;
+		i ^= (i & w) >> 1;
+		i = i.wrapping_mul(1 | seed >> 27);
+		i = i.wrapping_mul(0x6935fa69);
+		i ^= (i & w) >> 11;
+		i = i.wrapping_mul(0x74dcb303);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0x9e501cc3);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0xc860a3df);
+		i &= w;
+		i ^= i >> 5;
+
+		if i < len {
+			break
+		}
+	}
+
+	i.wrapping_add(seed) % len
+}
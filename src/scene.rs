@@ -1,26 +1,290 @@
 use crate::hittable::Hittable;
 use crate::math::ray::Ray;
 use crate::math::hit::HitResult;
-use crate::math::space::Vec3;
+use crate::math::aabb::Aabb;
 use std::fmt::Debug;
+use std::collections::HashMap;
+use crate::math::color::Color;
+use crate::math::space::{Pos, Vec3};
+use crate::shape::sphere::Sphere;
+use crate::shape::plane::{Plane, FinitePlane};
+use crate::shape::csg::CSG;
+use crate::shape::volume::Volume;
+use crate::medium::Medium;
+use crate::medium::fog::Fog;
+use crate::material::lambertian::Lambertian;
+use crate::material::glossy::Glossy;
+use crate::material::albedo::normals::Normals;
+use crate::material::albedo::pattern::Checker;
+use crate::material::albedo::procedural::{Procedural, ColorRamp};
+use crate::material::emissive::Emissive;
+use ultraviolet::DRotor3;
+use crate::environment::{Environment, Constant};
+use crate::bvh::Bvh;
+use crate::light::{Light, Lights};
+use crate::material::Material;
+use crate::math::color_space::ColorSpace;
 
 #[derive(Debug)]
 pub struct TestScene {
-	pub objects: Vec<Box<dyn Hittable>>
+	pub objects: Vec<Box<dyn Hittable>>,
+	pub environment: Box<dyn Environment>,
+	pub lights: Lights,
+	/// Space all colors are in, including the rendered ones.
+	pub working_space: ColorSpace,
+	/// Medium filling all of space, which every ray goes through.
+	pub fog: Option<Fog>,
+	materials: HashMap<MaterialKey, usize>
+}
+
+// materials are told apart by their address. Only the data pointer is used,
+// since the same material can end up with different vtables
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct MaterialKey(usize);
+
+impl MaterialKey {
+	fn new(material: &dyn Material) -> Self {
+		MaterialKey(material as *const dyn Material as *const () as usize)
+	}
 }
 
 impl TestScene {
 	pub fn new() -> Self {
-		TestScene { objects: vec![] }
+		TestScene {
+			objects: vec![],
+			environment: Box::new(Constant(Color::default())),
+			lights: Lights::default(),
+			working_space: ColorSpace::Rec709,
+			fog: None,
+			materials: HashMap::new()
+		}
+	}
+
+	/// Numbers every material in the scene, in the order the objects were
+	/// added. Has to be called again when objects are added.
+	pub fn collect_materials(&mut self) {
+		let mut materials = vec![];
+
+		for object in &self.objects {
+			object.collect_materials(&mut materials);
+		}
+
+		if let Some(fog) = &self.fog {
+			materials.push(&fog.medium);
+		}
+
+		let mut ids = HashMap::new();
+
+		for material in materials {
+			let next = ids.len();
+			ids.entry(MaterialKey::new(material)).or_insert(next);
+		}
+
+		self.materials = ids;
+	}
+
+	/// Light from the environment that makes it to the end of `ray`, which
+	/// didn't hit anything.
+	pub fn environment_light(&self, ray: &Ray) -> Color {
+		let color = self.environment.color(ray.dir.normalized());
+
+		match &self.fog {
+			Some(fog) => color * fog.transmittance_beyond(ray),
+			None => color
+		}
+	}
+
+	/// Number of a material found by `collect_materials`.
+	pub fn material_id(&self, material: &dyn Material) -> Option<usize> {
+		self.materials.get(&MaterialKey::new(material)).copied()
+	}
+
+	/// Finds every emissive object in the scene so that materials can sample
+	/// them directly. Has to be called again when objects are added.
+	pub fn collect_lights(&mut self) {
+		let mut lights = vec![];
+
+		for object in &self.objects {
+			object.collect_lights(&mut lights);
+		}
+
+		self.lights = Lights(lights);
+	}
+
+	/// Moves all of the objects into a `Bvh`, which makes big scenes a lot
+	/// faster to render.
+	pub fn build_bvh(&mut self) {
+		let objects = std::mem::take(&mut self.objects);
+		self.objects.push(Box::new(Bvh::new(objects)));
+	}
+
+	/// The scene shown by the interactive viewer and rendered by default.
+	pub fn example() -> Self {
+		let mut scene = TestScene::new();
+
+		scene.objects.push(Box::new(Plane {
+			center: Vec3::new(0., 0., 0.),
+			normal: Vec3::new(0., 1., 0.),
+			material: Box::new(Lambertian(Procedural::new(
+				Checker { size: 1. },
+				ColorRamp::between(Color(0.1, 0.1, 0.1), Color(0.07, 0.07, 0.07))
+			)))
+		}));
+
+		//scene.objects.push(Box::new(Sphere {
+		//	center: Vec3::new(-2., 1., 0.),
+		//	radius: 1.,
+		//	material: Box::new(Lambertian::solid(Color(1., 1., 1.)))
+		//}));
+
+		//scene.objects.push(Box::new(Sphere {
+		//	center: Vec3::new(0., 1., 0.),
+		//	radius: 1.,
+		//	material: Box::new(Normals())
+		//}));
+
+		//scene.objects.push(Box::new(Sphere {
+		//	center: Vec3::new(2., 1., 0.),
+		//	radius: 1.,
+		//	material: Box::new(Mirror(0.))
+		//}));
+
+		//scene.objects.push(Box::new(Plane {
+		//	center: Vec3::new(-15., 0., 0.),
+		//	normal: Vec3::new(1., 0., 0.),
+		//	material: Box::new(Solid(Color(0.9, 0.25, 0.25)))
+		//}));
+
+		//scene.objects.push(Box::new(Plane {
+		//	center: Vec3::new(15., 0., 0.),
+		//	normal: Vec3::new(-1., 0., 0.),
+		//	material: Box::new(Solid(Color(0.25, 0.25, 0.9)))
+		//}));
+
+		//scene.objects.push(Box::new(FinitePlane {
+		//	pos: Pos::new(Vec3::new(-4., 1., 0.), DRotor3::from_euler_angles(0., PI / 2., 0.)),
+		//	size: (1., 2.),
+		//	material: Box::new(Portal(Pos::new(Vec3::new(4., 0., 2.), DRotor3::identity())))
+		//}));
+
+		//for _ in 0..500 {
+		//	scene.objects.push(Box::new(Sphere {
+		//		center: Vec3::new(
+		//			rand::thread_rng().gen_range(-15., 15.),
+		//			0.5,
+		//			rand::thread_rng().gen_range(-15., 15.)
+		//		),
+		//		radius: 0.5,
+		//		material: Box::new(Normals {})
+		//	}));
+		//}
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(-3.75, 0.5, 0.),
+			radius: 0.5,
+			material: Box::new(Lambertian::solid(Color(0.25, 0.25, 1.0)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(-2.25, 0.5, 0.),
+			radius: 0.5,
+			material: Box::new(Emissive::solid(Color::splat(5.)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(-0.75, 0.5, 0.),
+			radius: 0.5,
+			material: Box::new(Lambertian::solid(Color(1.0, 0.25, 0.25)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(0.75, 0.5, 0.),
+			radius: 0.5,
+			material: Box::new(Emissive::solid(Color::splat(5.)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(2.25, 0.5, 0.),
+			radius: 0.5,
+			material: Box::new(Lambertian::solid(Color(0.25, 1.0, 0.25)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(3.75, 0.5, 0.),
+			radius: 0.5,
+			material: Box::new(Emissive::solid(Color::splat(5.)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(0., 2., 0.),
+			radius: 0.5,
+			material: Box::new(Glossy(Normals()))
+		}));
+
+		let csg1 = Sphere {
+			center: Vec3::new(-3., 2., 0.),
+			radius: 0.5,
+			material: Box::new(Lambertian(Normals()))
+		};
+
+		let csg2 = Sphere {
+			center: Vec3::new(-2.5, 2.5, 0.),
+			radius: 0.5,
+			material: Box::new(Lambertian::solid(Color::splat(0.75)))
+		};
+
+		let csghalve = Plane {
+			center: Vec3::new(-3., 2., 0.),
+			normal: Vec3::new(0., 0., 1.),
+			material: Box::new(Lambertian::solid(Color::splat(0.5)))
+		};
+
+		scene.objects.push(Box::new(CSG::subtract(
+			CSG::union(csg1, csg2),
+			csghalve
+		)));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(-3., 2., 0.),
+			radius: 0.125,
+			material: Box::new(Emissive::solid(Color::splat(5.)))
+		}));
+
+		scene.objects.push(Box::new(Sphere {
+			center: Vec3::new(-2.5, 2.5, 0.),
+			radius: 0.125,
+			material: Box::new(Emissive::solid(Color::splat(5.)))
+		}));
+
+		scene.objects.push(Box::new(Volume(Sphere {
+			center: Vec3::new(0., 4., 0.),
+			radius: 0.5,
+			material: Box::new(Lambertian::solid(Color::splat(1.)))
+		}, Medium::homogeneous(Color::default(), Color::splat(1.)))));
+
+		//scene.objects.push(Box::new(FinitePlane {
+		//	pos: Pos::new(Vec3::new(0., 5., 0.), DRotor3::identity()),
+		//	size: (10., 5.),
+		//	material: Box::new(Solid(Color(1.0, 1.0, 1.0)))
+		//}));
+
+		scene.objects.push(Box::new(FinitePlane {
+			pos: Pos::new(Vec3::new(0., 15., 0.), DRotor3::identity()),
+			size: (10., 5.),
+			material: Box::new(Emissive::solid(Color::splat(5.)))
+		}));
+
+		scene
 	}
 }
 
-impl Hittable for TestScene {
-	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+impl TestScene {
+	// closest of what `trace` finds on every object
+	fn closest_hit<'a>(&'a self, ray: &Ray, trace: impl Fn(&'a dyn Hittable, &Ray) -> Option<HitResult<'a>>) -> Option<HitResult<'a>> {
 		let mut hit: Option<HitResult> = None;
 
 		for object in &self.objects {
-			if let Some(this_hit) = object.ray_trace(ray) {
+			if let Some(this_hit) = trace(object.as_ref(), ray) {
 				assert!(this_hit.t >= 0., "HIT BEFORE START: {:#?}, {:#?}", object, this_hit);
 				assert!(this_hit.t < 1., "HIT AFTER END: {:#?}, {:#?}", object, this_hit);
 
@@ -36,6 +300,27 @@ impl Hittable for TestScene {
 
 		return hit
 	}
+}
+
+impl Hittable for TestScene {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		let hit = self.closest_hit(ray, |object, ray| object.ray_trace(ray));
+
+		match &self.fog {
+			Some(fog) => fog.ray_trace(ray, hit.map_or(1., |hit| hit.t)).or(hit),
+			None => hit
+		}
+	}
+
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		self.closest_hit(ray, |object, ray| object.ray_trace_surfaces(ray))
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		let fog = self.fog.as_ref().map_or(Color::splat(1.), |fog| fog.transmittance(ray));
+
+		self.objects.iter().fold(fog, |transmittance, object| transmittance * object.transmittance(ray))
+	}
 
 	fn is_inside(&self, point: Vec3) -> bool {
 		for object in &self.objects {
@@ -46,6 +331,33 @@ impl Hittable for TestScene {
 
 		false
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		let mut aabb: Option<Aabb> = None;
+
+		for object in &self.objects {
+			let object_aabb = object.bounding_box()?;
+			aabb = Some(aabb.map_or(object_aabb, |aabb| aabb.union(&object_aabb)));
+		}
+
+		aabb
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		for object in &self.objects {
+			object.collect_lights(lights);
+		}
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		for object in &self.objects {
+			object.collect_materials(materials);
+		}
+
+		if let Some(fog) = &self.fog {
+			materials.push(&fog.medium);
+		}
+	}
 }
 
 #[derive(Debug)]
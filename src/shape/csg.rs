@@ -4,49 +4,102 @@ use crate::math::hit::HitResult;
 use crate::math::space::Vec3;
 use std::fmt::Debug;
 use std::marker::PhantomData;
-use crate::scene::Couple;
+use crate::math::aabb::Aabb;
+use crate::math::color::Color;
+use crate::material::Material;
+
+/// Which of `Hittable::ray_trace` and `Hittable::ray_trace_surfaces` an
+/// `Operation` traces the shapes with.
+pub trait Trace {
+	fn trace<'a, S: Hittable>(shape: &'a S, ray: &Ray) -> Option<HitResult<'a>>;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Everything {}
+
+impl Trace for Everything {
+	fn trace<'a, S: Hittable>(shape: &'a S, ray: &Ray) -> Option<HitResult<'a>> {
+		shape.ray_trace(ray)
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Surfaces {}
+
+impl Trace for Surfaces {
+	fn trace<'a, S: Hittable>(shape: &'a S, ray: &Ray) -> Option<HitResult<'a>> {
+		shape.ray_trace_surfaces(ray)
+	}
+}
 
 pub trait Operation: Send + Sync + Debug {
-	fn ray_trace<'a, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>>;
+	fn ray_trace<'a, T: Trace, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>>;
+	fn transmittance<S1: Hittable, S2: Hittable>(ray: &Ray, s1: &S1, s2: &S2) -> Color;
 	fn is_inside<'a, S1: Hittable, S2: Hittable>(point: Vec3, s1: &'a S1, s2: &'a S2) -> bool;
+	fn bounding_box<S1: Hittable, S2: Hittable>(s1: &S1, s2: &S2) -> Option<Aabb>;
+}
+
+// what's left of `ray` after `hit`, which is on some portion of it. It's cut
+// from all of `ray` so that it still moves forward once the portions get
+// short, like when a surface is right at the end
+fn after(ray: &Ray, hit: &HitResult) -> Option<Ray> {
+	let t = ray.t_for(hit.pos()) + 1e-10;
+
+	if t < 1. {
+		Some(ray.from(t))
+	} else {
+		None
+	}
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum Union {}
 
 impl Operation for Union {
-	fn ray_trace<'a, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>> {
-		let couple = Couple::new(s1, s2);
+	fn ray_trace<'a, T: Trace, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>> {
+		let closest = |ray: &Ray| match (T::trace(s1, ray), T::trace(s2, ray)) {
+			(Some(h1), Some(h2)) => Some(h1.closer(h2)),
+			(o1, o2) => o1.or(o2)
+		};
 
-		let mut hit = couple.ray_trace(ray)?;
+		let mut hit = closest(ray)?;
 		let mut portion = *ray;
 
-		while couple.is_inside(portion.pos) {
-			hit = couple.ray_trace(&portion)?;
-			portion = hit.after_t();
+		while Self::is_inside(portion.pos, s1, s2) {
+			hit = closest(&portion)?;
+			portion = after(ray, &hit)?;
 		}
 
-		Some(hit)
+		Some(hit.map_onto_super(ray))
+	}
+
+	fn transmittance<S1: Hittable, S2: Hittable>(ray: &Ray, s1: &S1, s2: &S2) -> Color {
+		s1.transmittance(ray) * s2.transmittance(ray)
 	}
 
 	fn is_inside<'a, S1: Hittable, S2: Hittable>(point: Vec3, s1: &'a S1, s2: &'a S2) -> bool {
 		s1.is_inside(point) || s2.is_inside(point)
 	}
+
+	fn bounding_box<S1: Hittable, S2: Hittable>(s1: &S1, s2: &S2) -> Option<Aabb> {
+		Some(s1.bounding_box()?.union(&s2.bounding_box()?))
+	}
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum Subtract {}
 
 impl Operation for Subtract {
-	fn ray_trace<'a, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>> {
-		let mut o1 = s1.ray_trace(ray);
-		let mut o2 = s2.ray_trace(ray);
+	fn ray_trace<'a, T: Trace, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>> {
+		let mut o1 = T::trace(s1, ray);
+		let mut o2 = T::trace(s2, ray);
 
 		// ignore all intersections inside of the object
 		while let Some(h1) = &o1 {
 			if s2.is_inside(h1.pos()) {
-				o2 = s2.ray_trace(&h1.after_t());
-				o1 = s1.ray_trace(&h1.after_t());
+				let portion = after(ray, h1);
+				o2 = portion.and_then(|portion| T::trace(s2, &portion));
+				o1 = portion.and_then(|portion| T::trace(s1, &portion));
 			} else {
 				break
 			}
@@ -85,7 +138,7 @@ impl Operation for Subtract {
 					Some(h2flipped)
 				} else {
 					if s2.is_inside(h1.pos()) {
-						s2.ray_trace(&ray.from(h1.t + 1e-10))
+						T::trace(s2, &ray.from(h1.t + 1e-10))
 							.map(|h| h.map_onto_super(ray).flip())
 							.filter(|h| s1.is_inside(h.pos()))
 					} else {
@@ -106,9 +159,63 @@ impl Operation for Subtract {
 		}
 	}
 
+	fn transmittance<S1: Hittable, S2: Hittable>(ray: &Ray, s1: &S1, _s2: &S2) -> Color {
+		// whatever is inside of the cut is cut away along with it
+		s1.transmittance(ray)
+	}
+
 	fn is_inside<'a, S1: Hittable, S2: Hittable>(point: Vec3, s1: &'a S1, s2: &'a S2) -> bool {
 		s1.is_inside(point) && !s2.is_inside(point)
 	}
+
+	fn bounding_box<S1: Hittable, S2: Hittable>(s1: &S1, _s2: &S2) -> Option<Aabb> {
+		// cutting things away can only ever make the shape smaller
+		s1.bounding_box()
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Intersection {}
+
+impl Operation for Intersection {
+	fn ray_trace<'a, T: Trace, S1: Hittable, S2: Hittable>(ray: &Ray, s1: &'a S1, s2: &'a S2) -> Option<HitResult<'a>> {
+		let mut portion = *ray;
+
+		// the first surface of either shape that is inside of the other shape
+		// is the first surface of the intersection
+		loop {
+			let (hit, inside_other) = match (T::trace(s1, &portion), T::trace(s2, &portion)) {
+				(Some(h1), Some(h2)) if h2.t < h1.t => (h2, s1.is_inside(h2.pos())),
+				(Some(h1), _) => (h1, s2.is_inside(h1.pos())),
+				(None, Some(h2)) => (h2, s1.is_inside(h2.pos())),
+				(None, None) => return None
+			};
+
+			if inside_other {
+				return Some(hit.map_onto_super(ray))
+			}
+
+			portion = after(ray, &hit)?;
+		}
+	}
+
+	fn transmittance<S1: Hittable, S2: Hittable>(ray: &Ray, s1: &S1, s2: &S2) -> Color {
+		s1.transmittance(ray) * s2.transmittance(ray)
+	}
+
+	fn is_inside<'a, S1: Hittable, S2: Hittable>(point: Vec3, s1: &'a S1, s2: &'a S2) -> bool {
+		s1.is_inside(point) && s2.is_inside(point)
+	}
+
+	fn bounding_box<S1: Hittable, S2: Hittable>(s1: &S1, s2: &S2) -> Option<Aabb> {
+		match (s1.bounding_box(), s2.bounding_box()) {
+			// if the boxes don't overlap then the shape is empty, but an empty
+			// box still has to be somewhere
+			(Some(b1), Some(b2)) => Some(b1.intersection(&b2).unwrap_or(Aabb::new(b1.min, b1.min))),
+			(Some(aabb), None) | (None, Some(aabb)) => Some(aabb),
+			(None, None) => None
+		}
+	}
 }
 
 #[derive(Debug)]
@@ -126,12 +233,85 @@ impl<S1: Hittable, S2: Hittable> CSG<Subtract, S1, S2> {
 	}
 }
 
+impl<S1: Hittable, S2: Hittable> CSG<Intersection, S1, S2> {
+	pub fn intersection(shape1: S1, shape2: S2) -> Self {
+		CSG(shape1, shape2, PhantomData)
+	}
+}
+
 impl<O: Operation, S1: Hittable, S2: Hittable> Hittable for CSG<O, S1, S2> {
 	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
-		O::ray_trace(ray, &self.0, &self.1)
+		O::ray_trace::<Everything, _, _>(ray, &self.0, &self.1)
+	}
+
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		O::ray_trace::<Surfaces, _, _>(ray, &self.0, &self.1)
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		O::transmittance(ray, &self.0, &self.1)
 	}
 
 	fn is_inside(&self, point: Vec3) -> bool {
 		O::is_inside(point, &self.0, &self.1)
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		O::bounding_box(&self.0, &self.1)
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		self.0.collect_materials(materials);
+		self.1.collect_materials(materials);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::material::lambertian::Lambertian;
+	use crate::math::color::Color;
+	use crate::shape::sphere::Sphere;
+
+	fn sphere(x: f64) -> Sphere {
+		Sphere { center: Vec3::new(x, 0., 0.), radius: 1., material: Box::new(Lambertian::solid(Color::splat(0.5))) }
+	}
+
+	// rays from outside of `shape` hit it right where they start being inside
+	// of it, and never before
+	fn assert_hits_where_inside<S: Hittable>(shape: &S) {
+		let origin = Vec3::new(0.3, 0.2, 5.);
+
+		for i in 0..20 {
+			for j in 0..20 {
+				let target = Vec3::new(i as f64 / 10. - 1., j as f64 / 10. - 1., 0.);
+				let ray = Ray::new(origin, target - origin);
+				let hit = shape.ray_trace(&ray);
+				let end = hit.map_or(1., |hit| hit.t);
+
+				for step in 0..100 {
+					assert!(!shape.is_inside(ray.at(end * step as f64 / 100.)));
+				}
+
+				if let Some(hit) = hit {
+					assert!(shape.is_inside(ray.at(hit.t + 1e-6)));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn intersection_hits_where_inside() {
+		assert_hits_where_inside(&CSG::intersection(sphere(-0.5), sphere(0.5)));
+	}
+
+	#[test]
+	fn union_hits_where_inside() {
+		assert_hits_where_inside(&CSG::union(sphere(-0.5), sphere(0.5)));
+	}
+
+	#[test]
+	fn subtract_hits_where_inside() {
+		assert_hits_where_inside(&CSG::subtract(sphere(-0.5), sphere(0.5)));
+	}
 }
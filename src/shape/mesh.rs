@@ -0,0 +1,229 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::math::space::{Vec2, Vec3};
+use crate::math::ray::Ray;
+use crate::math::hit::HitResult;
+use crate::math::aabb::Aabb;
+use crate::hittable::Hittable;
+use crate::material::Material;
+use crate::shape::triangle::Triangle;
+use crate::bvh::Bvh;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Face {
+	pub positions: [usize; 3],
+	pub uvs: Option<[usize; 3]>,
+	pub normals: Option<[usize; 3]>,
+	pub material: usize
+}
+
+/// Vertex, texture coordinate and normal buffers shared by every triangle of a
+/// `Mesh`.
+#[derive(Debug, Default)]
+pub struct MeshData {
+	pub positions: Vec<Vec3>,
+	pub uvs: Vec<Vec2>,
+	pub normals: Vec<Vec3>,
+	pub faces: Vec<Face>,
+	pub materials: Vec<Box<dyn Material>>
+}
+
+impl MeshData {
+	fn vertices(&self, face: &Face) -> [Vec3; 3] {
+		[
+			self.positions[face.positions[0]],
+			self.positions[face.positions[1]],
+			self.positions[face.positions[2]]
+		]
+	}
+
+	/// Generates smooth vertex normals for every face that doesn't have any,
+	/// by averaging the area-weighted normals of all faces sharing a vertex.
+	pub fn compute_normals(&mut self) {
+		let base = self.normals.len();
+		let mut normals = vec![Vec3::zero(); self.positions.len()];
+
+		for face in self.faces.iter().filter(|f| f.normals.is_none()) {
+			let vertices = self.vertices(face);
+			// not normalized, so that bigger faces have more influence
+			let normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+
+			for &index in &face.positions {
+				normals[index] += normal;
+			}
+		}
+
+		self.normals.extend(normals.into_iter().map(|n| if n.mag_sq() > 0. { n.normalized() } else { n }));
+
+		for face in self.faces.iter_mut().filter(|f| f.normals.is_none()) {
+			let p = face.positions;
+			face.normals = Some([base + p[0], base + p[1], base + p[2]]);
+		}
+	}
+}
+
+struct MeshFace {
+	data: Arc<MeshData>,
+	index: usize
+}
+
+impl Debug for MeshFace {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "MeshFace #{}", self.index)
+	}
+}
+
+impl Hittable for MeshFace {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		let face = &self.data.faces[self.index];
+		let vertices = self.data.vertices(face);
+		let (t, u, v) = Triangle::intersect(&vertices, ray)?;
+		let geometric = Triangle::normal(&vertices);
+
+		let normal = match face.normals {
+			Some(indices) => {
+				let normals = &self.data.normals;
+				let smooth = (normals[indices[0]] * (1. - u - v) +
+					normals[indices[1]] * u +
+					normals[indices[2]] * v).normalized();
+
+				// keep the smooth normal on the same side as the actual surface
+				if smooth.dot(geometric) < 0. { -smooth } else { smooth }
+			}
+			None => geometric
+		};
+
+		// faces without texture coordinates use the barycentric coordinates
+		let uv = match face.uvs {
+			Some(indices) => {
+				let uvs = &self.data.uvs;
+				uvs[indices[0]] * (1. - u - v) + uvs[indices[1]] * u + uvs[indices[2]] * v
+			}
+			None => Vec2::new(u, v)
+		};
+
+		Some(HitResult {
+			ray: ray.clone(),
+			t,
+			normal,
+			uv,
+			material: self.data.materials[face.material].as_ref(),
+			object: None
+		})
+	}
+
+	fn is_inside(&self, _point: Vec3) -> bool {
+		false
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		Some(Triangle::bounds(&self.data.vertices(&self.data.faces[self.index])))
+	}
+}
+
+/// Triangle mesh with optional smooth normals. Closed meshes can be used in
+/// `CSG` and `Volume`, since `is_inside` counts how many times a ray leaving
+/// the point crosses the surface.
+#[derive(Debug)]
+pub struct Mesh {
+	data: Arc<MeshData>,
+	bvh: Bvh
+}
+
+impl Mesh {
+	pub fn new(data: MeshData) -> Self {
+		let data = Arc::new(data);
+
+		let faces = (0..data.faces.len())
+			.map(|index| Box::new(MeshFace { data: data.clone(), index }) as Box<dyn Hittable>)
+			.collect();
+
+		Mesh { data, bvh: Bvh::new(faces) }
+	}
+
+	pub fn data(&self) -> &MeshData {
+		&self.data
+	}
+}
+
+impl Hittable for Mesh {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		self.bvh.ray_trace(ray)
+	}
+
+	fn is_inside(&self, point: Vec3) -> bool {
+		let aabb = match self.bvh.bounding_box() {
+			Some(aabb) if aabb.contains(point) => aabb,
+			_ => return false
+		};
+
+		// odd direction so that the ray is unlikely to hit an edge exactly
+		let direction = Vec3::new(0.5413, 0.7317, 0.4143).normalized();
+		let mut ray = Ray::new(point, direction * (aabb.size().mag() * 2. + 1.));
+		let mut crossings = 0;
+
+		while let Some(hit) = self.bvh.ray_trace(&ray) {
+			crossings += 1;
+			ray = hit.after_t();
+		}
+
+		crossings % 2 == 1
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		self.bvh.bounding_box()
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		materials.extend(self.data.materials.iter().map(|m| m.as_ref()));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::material::lambertian::Lambertian;
+	use crate::math::color::Color;
+
+	// cube from -1 to 1, made of two triangles per side
+	fn cube() -> Mesh {
+		let mut data = MeshData::default();
+
+		for i in 0..8 {
+			let corner = |bit: usize| if i & bit == 0 { -1. } else { 1. };
+			data.positions.push(Vec3::new(corner(1), corner(2), corner(4)));
+		}
+
+		let sides = [[0, 1, 3, 2], [4, 6, 7, 5], [0, 4, 5, 1], [2, 3, 7, 6], [0, 2, 6, 4], [1, 5, 7, 3]];
+
+		for side in &sides {
+			for positions in &[[side[0], side[1], side[2]], [side[0], side[2], side[3]]] {
+				data.faces.push(Face { positions: *positions, uvs: None, normals: None, material: 0 });
+			}
+		}
+
+		data.materials.push(Box::new(Lambertian::solid(Color::splat(0.5))));
+		data.compute_normals();
+		Mesh::new(data)
+	}
+
+	#[test]
+	fn is_inside_a_closed_cube() {
+		let cube = cube();
+
+		// a grid that lines up with the edges and diagonals of the triangles,
+		// apart from the points right on the surface
+		for x in -6..=6 {
+			for y in -6..=6 {
+				for z in -6..=6 {
+					let point = Vec3::new(x as f64, y as f64, z as f64) * 0.25;
+					let inside = point.x.abs() < 1. && point.y.abs() < 1. && point.z.abs() < 1.;
+
+					if point.x.abs() != 1. && point.y.abs() != 1. && point.z.abs() != 1. {
+						assert_eq!(cube.is_inside(point), inside, "{:?}", point);
+					}
+				}
+			}
+		}
+	}
+}
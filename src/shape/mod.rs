@@ -2,3 +2,7 @@ pub mod sphere;
 pub mod plane;
 pub mod csg;
 pub mod volume;
+pub mod triangle;
+pub mod mesh;
+pub mod obj;
+pub mod moving;
@@ -0,0 +1,66 @@
+use crate::hittable::Hittable;
+use crate::math::hit::HitResult;
+use crate::math::space::Vec3;
+use crate::math::ray::Ray;
+use crate::math::aabb::Aabb;
+use crate::light::{Light, MovingLight};
+use crate::material::Material;
+use crate::math::color::Color;
+
+/// Moves a shape by the second field over the course of the exposure, so that
+/// it gets motion blurred. The shape is where it was built at time 0.
+///
+/// `is_inside` doesn't know about time and checks the shape at time 0.
+#[derive(Debug)]
+pub struct Moving<H: Hittable>(pub H, pub Vec3);
+
+impl<H: Hittable> Moving<H> {
+	fn offset(&self, time: f64) -> Vec3 {
+		self.1 * time
+	}
+}
+
+impl<H: Hittable> Hittable for Moving<H> {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		let offset = self.offset(ray.time);
+		let local = Ray { pos: ray.pos - offset, ..*ray };
+		let hit = self.0.ray_trace(&local)?;
+
+		Some(HitResult { ray: *ray, t: ray.t_for(hit.pos() + offset), ..hit })
+	}
+
+	fn ray_trace_surfaces(&self, ray: &Ray) -> Option<HitResult> {
+		let offset = self.offset(ray.time);
+		let local = Ray { pos: ray.pos - offset, ..*ray };
+		let hit = self.0.ray_trace_surfaces(&local)?;
+
+		Some(HitResult { ray: *ray, t: ray.t_for(hit.pos() + offset), ..hit })
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		let offset = self.offset(ray.time);
+		self.0.transmittance(&Ray { pos: ray.pos - offset, ..*ray })
+	}
+
+	fn is_inside(&self, point: Vec3) -> bool {
+		self.0.is_inside(point)
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		let aabb = self.0.bounding_box()?;
+		Some(aabb.union(&Aabb::new(aabb.min + self.1, aabb.max + self.1)))
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		let mut inner = vec![];
+		self.0.collect_lights(&mut inner);
+
+		for light in inner {
+			lights.push(Box::new(MovingLight { light, velocity: self.1 }));
+		}
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		self.0.collect_materials(materials)
+	}
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use crate::math::space::{Vec2, Vec3};
+use crate::material::Material;
+use crate::shape::mesh::{Mesh, MeshData, Face};
+
+#[derive(Debug)]
+pub enum ObjError {
+	Io(io::Error),
+	Parse { line: usize, message: String }
+}
+
+impl Display for ObjError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ObjError::Io(err) => write!(f, "{}", err),
+			ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message)
+		}
+	}
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+	fn from(err: io::Error) -> Self {
+		ObjError::Io(err)
+	}
+}
+
+/// Loads a Wavefront OBJ file into a `Mesh`. See `load`.
+pub fn open<P: AsRef<Path>, F: FnMut(&str) -> Box<dyn Material>>(path: P, material: F) -> Result<Mesh, ObjError> {
+	let mut data = load(BufReader::new(File::open(path)?), material)?;
+	data.compute_normals();
+	Ok(Mesh::new(data))
+}
+
+/// Parses the vertices, texture coordinates, normals and faces of an OBJ file.
+/// Polygons are triangulated as fans.
+///
+/// `material` is called once for every distinct `usemtl` or group name, and
+/// the returned material is used for all faces that follow it. Faces before
+/// any `usemtl` or `g` statement use the name `""`.
+pub fn load<R: BufRead, F: FnMut(&str) -> Box<dyn Material>>(reader: R, mut material: F) -> Result<MeshData, ObjError> {
+	let mut data = MeshData::default();
+	let mut material_indices: HashMap<String, usize> = HashMap::new();
+	let mut current_material = None;
+
+	for (line_index, line) in reader.lines().enumerate() {
+		let line = line?;
+		let line_number = line_index + 1;
+		let error = |message: String| ObjError::Parse { line: line_number, message };
+
+		let line = line.split('#').next().unwrap_or("").trim();
+		let mut words = line.split_whitespace();
+
+		let keyword = match words.next() {
+			Some(keyword) => keyword,
+			None => continue
+		};
+
+		match keyword {
+			"v" | "vn" => {
+				let mut component = || -> Result<f64, ObjError> {
+					let word = words.next().ok_or_else(|| error(format!("expected 3 components for {}", keyword)))?;
+					word.parse().map_err(|_| error(format!("invalid number {:?}", word)))
+				};
+
+				let vec = Vec3::new(component()?, component()?, component()?);
+
+				if keyword == "v" {
+					data.positions.push(vec);
+				} else {
+					data.normals.push(vec.normalized());
+				}
+			}
+			"vt" => {
+				let word = words.next().ok_or_else(|| error("expected at least 1 component for vt".into()))?;
+				let u = word.parse().map_err(|_| error(format!("invalid number {:?}", word)))?;
+
+				let v = match words.next() {
+					Some(word) => word.parse().map_err(|_| error(format!("invalid number {:?}", word)))?,
+					None => 0.
+				};
+
+				data.uvs.push(Vec2::new(u, v));
+			}
+			"usemtl" | "g" => {
+				current_material = Some(words.collect::<Vec<_>>().join(" "));
+			}
+			"f" => {
+				let name = current_material.get_or_insert_with(String::new);
+				let index = match material_indices.get(name) {
+					Some(&index) => index,
+					None => {
+						data.materials.push(material(name));
+						material_indices.insert(name.clone(), data.materials.len() - 1);
+						data.materials.len() - 1
+					}
+				};
+
+				let mut vertices = vec![];
+
+				for word in words {
+					vertices.push(parse_vertex(word, &data).map_err(error)?);
+				}
+
+				if vertices.len() < 3 {
+					return Err(error(format!("face has only {} vertices", vertices.len())))
+				}
+
+				for i in 1..vertices.len() - 1 {
+					let corners = [vertices[0], vertices[i], vertices[i + 1]];
+
+					let uvs = match (corners[0].1, corners[1].1, corners[2].1) {
+						(Some(a), Some(b), Some(c)) => Some([a, b, c]),
+						_ => None
+					};
+
+					let normals = match (corners[0].2, corners[1].2, corners[2].2) {
+						(Some(a), Some(b), Some(c)) => Some([a, b, c]),
+						_ => None
+					};
+
+					data.faces.push(Face {
+						positions: [corners[0].0, corners[1].0, corners[2].0],
+						uvs,
+						normals,
+						material: index
+					});
+				}
+			}
+			// smoothing groups, material libraries etc.
+			_ => {}
+		}
+	}
+
+	Ok(data)
+}
+
+// turns OBJ indices, which start at 1 and may be relative to the end, into
+// indices into the buffers
+fn resolve(word: &str, len: usize) -> Result<usize, String> {
+	let index: isize = word.parse().map_err(|_| format!("invalid index {:?}", word))?;
+
+	let resolved = if index < 0 {
+		len as isize + index
+	} else {
+		index - 1
+	};
+
+	if resolved < 0 || resolved as usize >= len {
+		Err(format!("index {} out of range", index))
+	} else {
+		Ok(resolved as usize)
+	}
+}
+
+// parses v, v/vt, v//vn and v/vt/vn
+fn parse_vertex(word: &str, data: &MeshData) -> Result<(usize, Option<usize>, Option<usize>), String> {
+	let mut parts = word.split('/');
+
+	let position = resolve(parts.next().unwrap_or(""), data.positions.len())?;
+
+	let uv = match parts.next() {
+		Some(uv) if !uv.is_empty() => Some(resolve(uv, data.uvs.len())?),
+		_ => None
+	};
+
+	let normal = match parts.next() {
+		Some(normal) if !normal.is_empty() => Some(resolve(normal, data.normals.len())?),
+		_ => None
+	};
+
+	Ok((position, uv, normal))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::material::lambertian::Lambertian;
+	use crate::math::color::Color;
+
+	fn parse(source: &str) -> Result<MeshData, ObjError> {
+		load(source.as_bytes(), |_| Box::new(Lambertian::solid(Color::splat(0.5))))
+	}
+
+	#[test]
+	fn resolves_negative_indices_and_missing_texture_coordinates() {
+		let data = parse("
+			v 0 0 0
+			v 1 0 0
+			v 1 1 0
+			v 0 1 0
+			vn 0 0 1
+			# quad split into two triangles, counting from the end
+			f -4//1 -3//1 -2//-1 -1//1
+		").unwrap();
+
+		assert_eq!(data.faces.len(), 2);
+		assert_eq!(data.faces[0].positions, [0, 1, 2]);
+		assert_eq!(data.faces[1].positions, [0, 2, 3]);
+		assert!(data.faces.iter().all(|face| face.uvs.is_none() && face.normals == Some([0, 0, 0])));
+	}
+
+	#[test]
+	fn rejects_indices_out_of_range() {
+		assert!(parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 4").is_err());
+		assert!(parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf -4 -3 -2").is_err());
+		assert!(parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 0 1 2").is_err());
+	}
+}
@@ -1,8 +1,10 @@
-use crate::math::space::{Vec3, Pos};
+use crate::math::space::{Vec2, Vec3, Pos};
 use crate::hittable::Hittable;
 use crate::math::ray::Ray;
 use crate::material::Material;
 use crate::math::hit::HitResult;
+use crate::math::aabb::Aabb;
+use crate::light::{Light, RectLight};
 
 #[derive(Debug)]
 pub struct Plane {
@@ -22,21 +24,45 @@ impl Plane {
 			Some(dist / ray.length())
 		}
 	}
+
+	// two vectors along the plane, which the texture coordinates follow
+	fn tangents(&self) -> (Vec3, Vec3) {
+		let helper = if self.normal.x.abs() > 0.9 { Vec3::new(0., 1., 0.) } else { Vec3::new(1., 0., 0.) };
+		let v = self.normal.cross(helper).normalized();
+
+		(v.cross(self.normal), v)
+	}
 }
 
 impl Hittable for Plane {
 	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
-		Plane::intersect(self.center, self.normal, ray).map(|t| HitResult {
-			ray: ray.clone(),
-			t,
-			normal: self.normal.clone(),
-			material: self.material.as_ref()
+		Plane::intersect(self.center, self.normal, ray).map(|t| {
+			// one unit of texture per unit of distance
+			let (tangent, bitangent) = self.tangents();
+			let rel = ray.at(t) - self.center;
+
+			HitResult {
+				ray: ray.clone(),
+				t,
+				normal: self.normal.clone(),
+				uv: Vec2::new(rel.dot(tangent), rel.dot(bitangent)),
+				material: self.material.as_ref(),
+				object: None
+			}
 		})
 	}
 
 	fn is_inside(&self, point: Vec3) -> bool {
 		(point - self.center).dot(self.normal) < 0.
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		None
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		materials.push(self.material.as_ref());
+	}
 }
 
 #[derive(Debug)]
@@ -60,11 +86,14 @@ impl Hittable for FinitePlane {
 			if local.x.abs() * 2. > self.size.0 || local.z.abs() * 2. > self.size.1 {
 				None
 			} else {
+				// stretched over the whole plane, with v going towards -Z
 				Some(HitResult {
 					ray: ray.clone(),
 					t,
 					normal,
-					material: self.material.as_ref()
+					uv: Vec2::new(local.x / self.size.0 + 0.5, 0.5 - local.z / self.size.1),
+					material: self.material.as_ref(),
+					object: None
 				})
 			}
 		})
@@ -74,4 +103,22 @@ impl Hittable for FinitePlane {
 		let rel = self.pos.inversed() * point;
 		rel.y < 0. && rel.x.abs() * 2. < self.size.0 && rel.z.abs() * 2. < self.size.1
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		let (x, z) = (self.size.0 / 2., self.size.1 / 2.);
+		let corners = [(-x, -z), (-x, z), (x, -z), (x, z)];
+
+		Aabb::from_points(corners.iter().map(|&(x, z)| self.pos * Vec3::new(x, 0., z)))
+			.map(|aabb| aabb.padded(1e-6))
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		if self.material.is_emissive() {
+			lights.push(Box::new(RectLight { pos: self.pos, size: self.size }));
+		}
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		materials.push(self.material.as_ref());
+	}
 }
\ No newline at end of file
@@ -1,8 +1,11 @@
-use crate::math::space::Vec3;
+use std::f64::consts::PI;
+use crate::math::space::{Vec2, Vec3};
 use crate::math::ray::Ray;
 use crate::hittable::Hittable;
 use crate::material::Material;
 use crate::math::hit::HitResult;
+use crate::math::aabb::Aabb;
+use crate::light::{Light, SphereLight};
 
 #[derive(Debug)]
 pub struct Sphere {
@@ -11,6 +14,18 @@ pub struct Sphere {
 	pub material: Box<dyn Material>
 }
 
+impl Sphere {
+	/// Longitude and latitude of a point on the unit sphere, with `u` going
+	/// around the Y axis starting at -X, and `v` going from the bottom to the
+	/// top.
+	pub fn uv(normal: Vec3) -> Vec2 {
+		let u = ((-normal.z).atan2(normal.x) + PI) / (2. * PI);
+		let v = (-normal.y).max(-1.).min(1.).acos() / PI;
+
+		Vec2::new(u, v)
+	}
+}
+
 impl Hittable for Sphere {
 	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
 		// quadratic equation stuff, basically copied from the tutorial
@@ -38,14 +53,18 @@ impl Hittable for Sphere {
 
 		let t = if closest_t >= 0. { closest_t } else { furthest_t };
 
-		if t < 0. || t * t > ray.length_squared() {
+		if t < 0. || t > 1. {
 			None
 		} else {
+			let normal = (ray.at(t) - self.center) / self.radius;
+
 			Some(HitResult {
 				ray: ray.clone(),
 				t,
-				normal: (ray.at(t) - self.center) / self.radius,
-				material: self.material.as_ref()
+				normal,
+				uv: Sphere::uv(normal),
+				material: self.material.as_ref(),
+				object: None
 			})
 		}
 	}
@@ -53,4 +72,18 @@ impl Hittable for Sphere {
 	fn is_inside(&self, point: Vec3) -> bool {
 		(point - self.center).mag_sq() < self.radius * self.radius
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		Some(Aabb::around(self.center, Vec3::broadcast(self.radius)))
+	}
+
+	fn collect_lights(&self, lights: &mut Vec<Box<dyn Light>>) {
+		if self.material.is_emissive() {
+			lights.push(Box::new(SphereLight { center: self.center, radius: self.radius }));
+		}
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		materials.push(self.material.as_ref());
+	}
 }
\ No newline at end of file
@@ -0,0 +1,85 @@
+use crate::math::space::{Vec2, Vec3};
+use crate::math::ray::Ray;
+use crate::hittable::Hittable;
+use crate::material::Material;
+use crate::math::hit::HitResult;
+use crate::math::aabb::Aabb;
+
+/// Single flat triangle. Counter-clockwise winding is the front side.
+#[derive(Debug)]
+pub struct Triangle {
+	pub vertices: [Vec3; 3],
+	pub material: Box<dyn Material>
+}
+
+impl Triangle {
+	/// Möller–Trumbore intersection. Returns `t` and the barycentric
+	/// coordinates of the second and third vertex.
+	pub fn intersect(vertices: &[Vec3; 3], ray: &Ray) -> Option<(f64, f64, f64)> {
+		let edge1: Vec3 = vertices[1] - vertices[0];
+		let edge2: Vec3 = vertices[2] - vertices[0];
+
+		let p = ray.dir.cross(edge2);
+		let det = edge1.dot(p);
+
+		if det.abs() < 1e-12 {
+			return None
+		}
+
+		let inv_det = 1. / det;
+		let s: Vec3 = ray.pos - vertices[0];
+		let u = s.dot(p) * inv_det;
+
+		if u < 0. || u > 1. {
+			return None
+		}
+
+		let q = s.cross(edge1);
+		let v = ray.dir.dot(q) * inv_det;
+
+		if v < 0. || u + v > 1. {
+			return None
+		}
+
+		let t = edge2.dot(q) * inv_det;
+
+		if t < 0. || t > 1. {
+			None
+		} else {
+			Some((t, u, v))
+		}
+	}
+
+	pub fn normal(vertices: &[Vec3; 3]) -> Vec3 {
+		(vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]).normalized()
+	}
+
+	pub fn bounds(vertices: &[Vec3; 3]) -> Aabb {
+		Aabb::new(vertices[0], vertices[1]).grow(vertices[2]).padded(1e-6)
+	}
+}
+
+impl Hittable for Triangle {
+	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
+		Triangle::intersect(&self.vertices, ray).map(|(t, u, v)| HitResult {
+			ray: ray.clone(),
+			t,
+			normal: Triangle::normal(&self.vertices),
+			uv: Vec2::new(u, v),
+			material: self.material.as_ref(),
+			object: None
+		})
+	}
+
+	fn is_inside(&self, _point: Vec3) -> bool {
+		false
+	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		Some(Triangle::bounds(&self.vertices))
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		materials.push(self.material.as_ref());
+	}
+}
@@ -1,48 +1,145 @@
 use crate::hittable::Hittable;
 use crate::math::hit::HitResult;
-use crate::math::space::Vec3;
+use crate::math::color::Color;
+use crate::math::space::{Vec2, Vec3};
 use crate::math::ray::Ray;
-use crate::rng::{RngRangeWyrand, gen_wyrand_once};
-use crate::material::lambertian::Lambertian;
-use crate::material::albedo::solid::Solid;
+use crate::rng::gen_sampler_once;
+use crate::math::aabb::Aabb;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::medium::density::Density;
 
+/// Fills a closed shape with a participating medium. Rays hit the medium
+/// wherever they collide with it inside of the shape, and pass through the
+/// shape's surface as if it wasn't there, so its material isn't used.
+///
+/// Collisions are found with delta tracking, which steps through the medium
+/// as if it were as dense as its majorant everywhere and rejects steps in
+/// proportion to how much thinner it actually is. Transmittance for shadow
+/// rays uses ratio tracking, which takes the same steps but scales the light
+/// down at every one of them instead. Both are unbiased for any density.
 #[derive(Debug)]
-pub struct Volume<H: Hittable>(pub H, pub f64);
+pub struct Volume<H: Hittable, D: Density>(pub H, pub Medium<D>);
 
-impl<H: Hittable> Hittable for Volume<H> {
+impl<H: Hittable, D: Density> Volume<H, D> {
+	// calls `visit` with the range of `t` of every stretch of `ray` inside of
+	// the shape in order, until it returns false. Relies on the shape being
+	// closed, so that its surface alternates between letting rays in and out
+	fn for_each_segment(&self, ray: &Ray, mut visit: impl FnMut(f64, f64) -> bool) {
+		let mut start = if self.0.is_inside(ray.pos) { Some(0.) } else { None };
+		let mut t = 0.;
+
+		loop {
+			let boundary = self.0.ray_trace(&ray.from(t)).map(|hit| hit.map_onto_super(ray).t);
+
+			match (start, boundary) {
+				(Some(start_t), boundary) => {
+					let end_t = boundary.unwrap_or(1.);
+
+					if !visit(start_t, end_t) || boundary.is_none() {
+						return
+					}
+
+					start = None;
+					t = end_t + 1e-10;
+				}
+				(None, Some(start_t)) => {
+					start = Some(start_t);
+					t = start_t + 1e-10;
+				}
+				(None, None) => return
+			}
+		}
+	}
+
+	// distance in `t` to the next tentative collision
+	fn step(&self, ray: &Ray, majorant: f64) -> f64 {
+		-(1. - gen_sampler_once()).ln() / (majorant * ray.length())
+	}
+}
+
+impl<H: Hittable, D: Density> Hittable for Volume<H, D> {
 	fn ray_trace(&self, ray: &Ray) -> Option<HitResult> {
-		let hit = self.0.ray_trace(ray)?;
-		let start_t;
-		let end_t;
-
-		if self.0.is_inside(ray.pos) {
-			start_t = 0.;
-			end_t = hit.t;
-		} else {
-			start_t = hit.t;
-			end_t = self.0.ray_trace(&ray.from(start_t + 1e-10))?.map_onto_super(ray).t;
+		let majorant = self.1.majorant();
+
+		if majorant <= 0. {
+			return None
 		}
 
-		assert!(end_t > start_t, "end_t ({}) < start_t ({})", end_t, start_t);
+		let mut collision = None;
 
-		let guaranteed_t = self.1 / ray.length();
-		let dist_t = end_t - start_t;
+		self.for_each_segment(ray, |start, end| {
+			let mut t = start;
 
-		if gen_wyrand_once() < dist_t / guaranteed_t {
-			let t = RngRangeWyrand::once(start_t, end_t);
-			let mut normal = Lambertian::<Solid>::random_vec();
+			loop {
+				t += self.step(ray, majorant);
 
-			if normal.dot(ray.dir) > 0. {
-				normal = -normal;
+				if t >= end {
+					return true
+				}
+
+				let extinction = self.1.extinction_at(ray.at(t));
+
+				if gen_sampler_once() * majorant < extinction.0.max(extinction.1).max(extinction.2) {
+					collision = Some(t);
+					return false
+				}
 			}
+		});
+
+		collision.map(|t| HitResult {
+			ray: *ray,
+			t,
+			// facing back, so that anything offset along it stays put
+			normal: -ray.dir.normalized(),
+			uv: Vec2::default(),
+			material: &self.1,
+			object: None
+		})
+	}
+
+	fn ray_trace_surfaces(&self, _ray: &Ray) -> Option<HitResult> {
+		None
+	}
+
+	fn transmittance(&self, ray: &Ray) -> Color {
+		let majorant = self.1.majorant();
+		let mut transmittance = Color::splat(1.);
 
-			Some(HitResult { ray: *ray, t, normal, material: hit.material })
-		} else {
-			None
+		if majorant <= 0. {
+			return transmittance
 		}
+
+		self.for_each_segment(ray, |start, end| {
+			let mut t = start;
+
+			loop {
+				t += self.step(ray, majorant);
+
+				if t >= end {
+					return true
+				}
+
+				transmittance *= Color::splat(1.) - self.1.extinction_at(ray.at(t)) / majorant;
+
+				if transmittance == Color::default() {
+					return false
+				}
+			}
+		});
+
+		transmittance
 	}
 
 	fn is_inside(&self, point: Vec3) -> bool {
 		self.0.is_inside(point)
 	}
+
+	fn bounding_box(&self) -> Option<Aabb> {
+		self.0.bounding_box()
+	}
+
+	fn collect_materials<'a>(&'a self, materials: &mut Vec<&'a dyn Material>) {
+		materials.push(&self.1)
+	}
 }